@@ -0,0 +1,465 @@
+use crate::{
+    dimacs::{decompress, open, tokens, ParseError, ParseErrorKind, MAX_VARIABLE},
+    Error,
+};
+use fixedbitset::FixedBitSet;
+use std::{
+    fmt::{self, Formatter},
+    io::Read,
+    path::Path,
+};
+
+/// A sequential circuit made of AND gates, inverters and latches, as read
+/// from an AIGER file. Literals are numbered as in AIGER: variable `v` is
+/// `2v` and its negation `2v + 1`, with 0 and 1 the constants false and
+/// true.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Aiger {
+    pub max_variable: usize,
+    pub inputs: Vec<usize>,
+    pub latches: Vec<Latch>,
+    pub outputs: Vec<usize>,
+    /// The bad-state properties, which a safe circuit never lets become true
+    pub bad: Vec<usize>,
+    /// Invariant constraints, which have to hold at every step of a trace
+    pub constraints: Vec<usize>,
+    /// The AND gates, each given by its output and its two inputs
+    pub ands: Vec<(usize, usize, usize)>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Latch {
+    pub literal: usize,
+    /// The literal whose value the latch takes at the next step
+    pub next: usize,
+    /// The value of the latch in the initial state, or `None` if it can
+    /// start out either way
+    pub reset: Option<bool>,
+}
+
+/// The ways in which an AIGER file can fail to be valid
+#[derive(Clone, Debug, PartialEq)]
+pub enum ErrorKind {
+    /// The file doesn't start with `aag` or `aig`
+    MissingHeader,
+    /// The header isn't of the form `aag <M> <I> <L> <O> <A>`, followed by
+    /// up to four more counts, or a binary file's variables aren't numbered
+    /// without gaps
+    MalformedHeader,
+    /// A line contains something other than a non-negative integer
+    InvalidLiteral,
+    /// A line has the wrong number of literals for its section, defines a
+    /// variable that is already defined, or defines something other than a
+    /// positive literal, or the file ends before its last section
+    InvalidDefinition,
+}
+
+impl ErrorKind {
+    pub(crate) fn describe(&self, f: &mut Formatter, found: &str) -> fmt::Result {
+        match self {
+            ErrorKind::MissingHeader => {
+                write!(f, "expected an `aag` or `aig` header, found {}", found)
+            }
+            ErrorKind::MalformedHeader => write!(
+                f,
+                "expected a header of the form `aag <M> <I> <L> <O> <A> [<B> <C> <J> <F>]`, \
+                 found {}",
+                found
+            ),
+            ErrorKind::InvalidLiteral => write!(
+                f,
+                "expected a literal (a non-negative integer), found {}",
+                found
+            ),
+            ErrorKind::InvalidDefinition => write!(
+                f,
+                "expected the literals defining an input, latch, output or gate, found {}",
+                found
+            ),
+        }
+    }
+}
+
+pub fn parse_file(path: impl AsRef<Path>) -> Result<Aiger, Error> {
+    read(open(path.as_ref())?)
+}
+
+/// Reads a circuit in AIGER format, decompressing it as in `dimacs::read`
+pub fn read(reader: impl Read) -> Result<Aiger, Error> {
+    let mut data = Vec::new();
+    decompress(reader)?.read_to_end(&mut data)?;
+    parse(&data)
+}
+
+/// Parses a circuit in either AIGER format: the ASCII one, with an `aag`
+/// header and every definition on a line of its own, or the binary one,
+/// with an `aig` header, in which inputs, latches and gates are numbered in
+/// order and gates are given as deltas in a compact binary encoding.
+/// Justice and fairness properties are skipped, as are the symbol table and
+/// comments.
+pub fn parse(data: &[u8]) -> Result<Aiger, Error> {
+    let mut parser = Parser {
+        data,
+        pos: 0,
+        line: 0,
+        max_variable: 0,
+        defined: FixedBitSet::default(),
+    };
+    Ok(parser.parse()?)
+}
+
+struct Parser<'a> {
+    data: &'a [u8],
+    pos: usize,
+    /// The number of the last line read
+    line: usize,
+    max_variable: usize,
+    /// The variables defined so far
+    defined: FixedBitSet,
+}
+
+impl Parser<'_> {
+    fn parse(&mut self) -> Result<Aiger, ParseError> {
+        let (binary, counts) = self.parse_header()?;
+        let [max_variable, num_inputs, num_latches, num_outputs, num_ands, properties @ ..] =
+            counts;
+        let [num_bad, num_constraints, num_justice, num_fairness] = properties;
+        let mut aiger = Aiger {
+            max_variable,
+            ..Aiger::default()
+        };
+
+        for idx in 0..num_inputs {
+            aiger.inputs.push(match binary {
+                true => 2 * (idx + 1),
+                false => {
+                    let literals = self.literals(1, 1)?;
+                    self.define(literals[0])?
+                }
+            });
+        }
+        for idx in 0..num_latches {
+            let (literal, rest) = match binary {
+                true => (2 * (num_inputs + idx + 1), self.literals(1, 2)?),
+                false => {
+                    let literals = self.literals(2, 3)?;
+                    (self.define(literals[0])?, literals[1..].to_vec())
+                }
+            };
+            let reset = match rest.get(1) {
+                None | Some((_, 0)) => Some(false),
+                Some((_, 1)) => Some(true),
+                Some((_, reset)) if *reset == literal => None,
+                Some((column, reset)) => {
+                    return Err(self.error(
+                        ParseErrorKind::Aiger(ErrorKind::InvalidDefinition),
+                        *column,
+                        *reset,
+                    ))
+                }
+            };
+            aiger.latches.push(Latch {
+                literal,
+                next: rest[0].1,
+                reset,
+            });
+        }
+        for (count, literals) in [
+            (num_outputs, &mut aiger.outputs),
+            (num_bad, &mut aiger.bad),
+            (num_constraints, &mut aiger.constraints),
+        ] {
+            for _ in 0..count {
+                literals.push(self.literals(1, 1)?[0].1);
+            }
+        }
+        let mut num_skipped = num_fairness;
+        for _ in 0..num_justice {
+            num_skipped = num_skipped.saturating_add(self.literals(1, 1)?[0].1);
+        }
+        for _ in 0..num_skipped {
+            self.literals(1, 1)?;
+        }
+
+        for idx in 0..num_ands {
+            aiger.ands.push(match binary {
+                true => {
+                    let output = 2 * (num_inputs + num_latches + idx + 1);
+                    let end = ParseError::at_end(
+                        ParseErrorKind::Aiger(ErrorKind::InvalidDefinition),
+                        self.line + 1,
+                    );
+                    let left = self.delta(output).ok_or_else(|| end.clone())?;
+                    let right = self.delta(left).ok_or(end)?;
+                    (output, left, right)
+                }
+                false => {
+                    let literals = self.literals(3, 3)?;
+                    (self.define(literals[0])?, literals[1].1, literals[2].1)
+                }
+            });
+        }
+        Ok(aiger)
+    }
+
+    /// Whether the file is binary, and the nine counts of its header, the
+    /// last four of which are optional
+    fn parse_header(&mut self) -> Result<(bool, [usize; 9]), ParseError> {
+        let line = self.next_line().unwrap_or_default();
+        let tokens = tokens(&line);
+        let error = |kind, idx: usize| match tokens.get(idx) {
+            Some((column, token)) => ParseError {
+                kind,
+                line: 1,
+                column: *column,
+                token: (*token).to_owned(),
+            },
+            None => ParseError {
+                column: line.chars().count() + 1,
+                ..ParseError::at_end(kind, 1)
+            },
+        };
+
+        let binary = match tokens.first().map(|(_, token)| *token) {
+            Some("aag") => false,
+            Some("aig") => true,
+            _ => return Err(error(ParseErrorKind::Aiger(ErrorKind::MissingHeader), 0)),
+        };
+        if tokens.len() < 6 {
+            return Err(error(
+                ParseErrorKind::Aiger(ErrorKind::MalformedHeader),
+                tokens.len(),
+            ));
+        }
+        if tokens.len() > 10 {
+            return Err(error(ParseErrorKind::Aiger(ErrorKind::MalformedHeader), 10));
+        }
+        let mut counts = [0usize; 9];
+        for (idx, (_, token)) in tokens.iter().enumerate().skip(1) {
+            counts[idx - 1] = token
+                .parse()
+                .map_err(|_| error(ParseErrorKind::Aiger(ErrorKind::MalformedHeader), idx))?;
+        }
+
+        // Every literal has to fit, and binary files number every variable in
+        // order, leaving no gaps. Nothing in a binary file backs its inputs,
+        // so there can't be more of them than could be believed.
+        let [max_variable, num_inputs, num_latches, _, num_ands, ..] = counts;
+        let fits = max_variable
+            .checked_mul(2)
+            .and_then(|max| max.checked_add(1));
+        let numbered = num_inputs
+            .checked_add(num_latches)
+            .and_then(|sum| sum.checked_add(num_ands));
+        if fits.is_none() || binary && numbered != Some(max_variable) {
+            return Err(error(ParseErrorKind::Aiger(ErrorKind::MalformedHeader), 1));
+        }
+        if binary && num_inputs > MAX_VARIABLE {
+            return Err(error(ParseErrorKind::Aiger(ErrorKind::MalformedHeader), 2));
+        }
+        self.max_variable = max_variable;
+        Ok((binary, counts))
+    }
+
+    fn next_line(&mut self) -> Option<String> {
+        let rest = self.data.get(self.pos..).filter(|rest| !rest.is_empty())?;
+        let end = rest.iter().position(|b| *b == b'\n').unwrap_or(rest.len());
+        self.pos += end + 1;
+        self.line += 1;
+        Some(String::from_utf8_lossy(&rest[..end]).into_owned())
+    }
+
+    /// The literals on the next line, along with the columns they start at,
+    /// of which there have to be between `min` and `max`
+    fn literals(&mut self, min: usize, max: usize) -> Result<Vec<(usize, usize)>, ParseError> {
+        let kind = ParseErrorKind::Aiger(ErrorKind::InvalidDefinition);
+        let line = self
+            .next_line()
+            .ok_or_else(|| ParseError::at_end(kind.clone(), self.line + 1))?;
+        let tokens = tokens(&line);
+        let error = |kind, idx: usize| match tokens.get(idx) {
+            Some((column, token)) => ParseError {
+                kind,
+                line: self.line,
+                column: *column,
+                token: (*token).to_owned(),
+            },
+            None => ParseError {
+                column: line.chars().count() + 1,
+                ..ParseError::at_end(kind, self.line)
+            },
+        };
+
+        if tokens.len() < min {
+            return Err(error(kind, tokens.len()));
+        }
+        if tokens.len() > max {
+            return Err(error(kind, max));
+        }
+        let mut literals = Vec::new();
+        for (idx, (column, token)) in tokens.iter().enumerate() {
+            let literal = token
+                .parse::<usize>()
+                .map_err(|_| error(ParseErrorKind::Aiger(ErrorKind::InvalidLiteral), idx))?;
+            if literal / 2 > self.max_variable {
+                let kind = ParseErrorKind::VariableOutOfRange(self.max_variable);
+                return Err(error(kind, idx));
+            }
+            literals.push((*column, literal));
+        }
+        Ok(literals)
+    }
+
+    /// Notes that the variable of `literal`, which was just read, is defined,
+    /// returning the literal
+    fn define(&mut self, (column, literal): (usize, usize)) -> Result<usize, ParseError> {
+        self.defined.grow(literal / 2 + 1);
+        if literal < 2 || literal % 2 == 1 || self.defined.put(literal / 2) {
+            return Err(self.error(
+                ParseErrorKind::Aiger(ErrorKind::InvalidDefinition),
+                column,
+                literal,
+            ));
+        }
+        Ok(literal)
+    }
+
+    /// The literal a binary delta is taken from, which can't be negative
+    fn delta(&mut self, literal: usize) -> Option<usize> {
+        let mut delta = 0usize;
+        for shift in (0..64).step_by(7) {
+            let byte = *self.data.get(self.pos)?;
+            self.pos += 1;
+            delta |= ((byte & 0x7f) as usize).checked_shl(shift)?;
+            if byte & 0x80 == 0 {
+                return literal.checked_sub(delta);
+            }
+        }
+        None
+    }
+
+    /// An error with the literal at `column` of the last line read
+    fn error(&self, kind: ParseErrorKind, column: usize, literal: usize) -> ParseError {
+        ParseError {
+            kind,
+            line: self.line,
+            column,
+            token: literal.to_string(),
+        }
+    }
+}
+
+#[test]
+fn parse_ascii_and_binary() -> Result<(), Error> {
+    // The input and the latch feeding into a gate, whose output is bad
+    let expected = Aiger {
+        max_variable: 3,
+        inputs: vec![2],
+        latches: vec![Latch {
+            literal: 4,
+            next: 3,
+            reset: Some(true),
+        }],
+        outputs: vec![],
+        bad: vec![6],
+        constraints: vec![],
+        ands: vec![(6, 4, 2)],
+    };
+    let ascii = parse(b"aag 3 1 1 0 1 1\n2\n4 3 1\n6\n6 4 2\ni0 request\nc\nanything\n")?;
+    assert_eq!(ascii, expected);
+    let binary = parse(b"aig 3 1 1 0 1 1\n3 1\n6\n\x02\x02i0 request\n")?;
+    assert_eq!(binary, expected);
+
+    // Uninitialized latches, constraints, and justice properties
+    let aiger = parse(b"aag 2 1 1 1 0 0 1 1 1\n2\n4 2 4\n5\n3\n1\n2\n4\n")?;
+    assert_eq!(aiger.latches[0].reset, None);
+    assert_eq!(aiger.outputs, vec![5]);
+    assert_eq!(aiger.constraints, vec![3]);
+    let aiger = parse(b"aag 3 1 0 0 2 1 0 2 0\n2\n4\n1\n2\n2\n4\n6\n4 2 2\n6 5 3\n")?;
+    assert_eq!(aiger.bad, vec![4]);
+    assert_eq!(aiger.ands, vec![(4, 2, 2), (6, 5, 3)]);
+    Ok(())
+}
+
+#[test]
+fn aiger_errors() {
+    use crate::dimacs::parse_error;
+    let error = |data: &[u8]| parse_error(parse(data));
+    assert_eq!(
+        error(b"p cnf 1 1\n"),
+        (
+            ParseErrorKind::Aiger(ErrorKind::MissingHeader),
+            1,
+            1,
+            "p".to_owned()
+        )
+    );
+    assert_eq!(
+        error(b"aag 1 1 0\n"),
+        (
+            ParseErrorKind::Aiger(ErrorKind::MalformedHeader),
+            1,
+            10,
+            String::new()
+        )
+    );
+    assert_eq!(
+        error(b"aig 2 1 0 0 0\n"),
+        (
+            ParseErrorKind::Aiger(ErrorKind::MalformedHeader),
+            1,
+            5,
+            "2".to_owned()
+        )
+    );
+    // Counts that overflow when they're added up or made into literals
+    assert_eq!(
+        error(b"aig 0 18446744073709551615 1 0 0\n"),
+        (
+            ParseErrorKind::Aiger(ErrorKind::MalformedHeader),
+            1,
+            5,
+            "0".to_owned()
+        )
+    );
+    assert_eq!(
+        error(b"aag 18446744073709551615 0 0 0 0\n"),
+        (
+            ParseErrorKind::Aiger(ErrorKind::MalformedHeader),
+            1,
+            5,
+            "18446744073709551615".to_owned()
+        )
+    );
+    assert_eq!(
+        error(b"aag 1 2 0 0 0\n2\n2\n"),
+        (
+            ParseErrorKind::Aiger(ErrorKind::InvalidDefinition),
+            3,
+            1,
+            "2".to_owned()
+        )
+    );
+    assert_eq!(
+        error(b"aag 1 0 0 1 0\n4\n"),
+        (ParseErrorKind::VariableOutOfRange(1), 2, 1, "4".to_owned())
+    );
+    assert_eq!(
+        error(b"aag 3 1 0 0 1\n2\n6 2\n"),
+        (
+            ParseErrorKind::Aiger(ErrorKind::InvalidDefinition),
+            3,
+            4,
+            String::new()
+        )
+    );
+    assert_eq!(
+        error(b"aig 2 1 0 0 1\n\x02"),
+        (
+            ParseErrorKind::Aiger(ErrorKind::InvalidDefinition),
+            2,
+            1,
+            String::new()
+        )
+    );
+}
@@ -5,6 +5,8 @@ pub struct Assignment {
     sign: Sign,
     antecedent: Option<ClauseIdx>,
     decision_level: DecisionLevel,
+    /// When the assignment was made, relative to the others
+    order: usize,
 }
 
 impl Assignment {
@@ -13,6 +15,7 @@ impl Assignment {
             sign,
             antecedent: None,
             decision_level,
+            order: 0,
         }
     }
 
@@ -21,6 +24,7 @@ impl Assignment {
             sign,
             antecedent: Some(antecedent),
             decision_level,
+            order: 0,
         }
     }
 
@@ -35,4 +39,13 @@ impl Assignment {
     pub fn decision_level(&self) -> DecisionLevel {
         self.decision_level
     }
+
+    /// Orders the assignments currently in place by when they were made
+    pub fn order(&self) -> usize {
+        self.order
+    }
+
+    pub(crate) fn with_order(self, order: usize) -> Self {
+        Self { order, ..self }
+    }
 }
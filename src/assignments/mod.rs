@@ -12,6 +12,8 @@ pub use assignment::Assignment;
 pub struct Assignments {
     assignments: Vec<Option<Assignment>>,
     last_sign: FixedBitSet,
+    /// The number of assignments ever made, which orders them
+    num_made: usize,
 }
 
 impl Assignments {
@@ -19,9 +21,15 @@ impl Assignments {
         Self {
             assignments: vec![None; num_vars as usize],
             last_sign: FixedBitSet::with_capacity(num_vars as usize),
+            num_made: 0,
         }
     }
 
+    pub fn grow(&mut self, num_vars: Variable) {
+        self.assignments.resize(num_vars, None);
+        self.last_sign.grow(num_vars);
+    }
+
     pub fn get(&self, var: Variable) -> Option<&Assignment> {
         self[var].as_ref()
     }
@@ -42,7 +50,8 @@ impl Assignments {
                     history.add(literal);
                 }
                 self.last_sign.set(var, assignment.sign().into());
-                self.assignments[var] = Some(assignment);
+                self.num_made += 1;
+                self.assignments[var] = Some(assignment.with_order(self.num_made));
                 Status::Ok
             }
             Some(existing) if existing.sign() != assignment.sign() => assignment
@@ -70,10 +79,10 @@ impl Assignments {
         counters.add_to_heap(var);
     }
 
-    pub fn assignments(self) -> impl Iterator<Item = (Variable, Sign)> {
+    pub fn assignments<'a>(&'a self) -> impl Iterator<Item = (Variable, Sign)> + 'a {
         self.assignments
-            .into_iter()
-            .map(|assignment| assignment.unwrap().sign())
+            .iter()
+            .map(|assignment| assignment.as_ref().unwrap().sign())
             .enumerate()
     }
 
@@ -1,29 +1,390 @@
-use std::time::Instant;
+use std::{fs::File, io::Read, time::Instant};
 
-use sat::*;
+use sat::{
+    proof::{Checker, Format, Proof},
+    *,
+};
 
-fn main() -> Result<(), String> {
+const USAGE: &str = "<problem file> [--proof <proof file>] [--proof-format <drat|binary|lrat>] \
+                     [--verify] [--core <core file>] [--strict]";
+const CHECK_USAGE: &str =
+    "check-proof <problem file> <proof file> [--proof-format <drat|binary|lrat>] [--core <core file>]";
+const MUS_USAGE: &str = "mus <problem file>";
+const MAXSAT_USAGE: &str = "maxsat <wcnf file>";
+const OPB_USAGE: &str = "opb <opb file>";
+const ICNF_USAGE: &str = "icnf <icnf file>";
+const QBF_USAGE: &str = "qbf <qdimacs file>";
+const BMC_USAGE: &str = "bmc <aiger file> <bound>";
+
+/// Command line options for solving a problem
+#[derive(Default)]
+struct Options {
+    problem: String,
+    proof: Option<String>,
+    proof_format: Option<Format>,
+    verify: bool,
+    core: Option<String>,
+    strict: bool,
+}
+
+impl Options {
+    fn parse(args: &[String]) -> Option<Self> {
+        let mut options = Self::default();
+        let mut problem = None;
+        let mut args = args.iter();
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--proof" => options.proof = Some(args.next()?.clone()),
+                "--proof-format" => options.proof_format = Some(args.next()?.parse().ok()?),
+                "--verify" => options.verify = true,
+                "--core" => options.core = Some(args.next()?.clone()),
+                "--strict" => options.strict = true,
+                _ if problem.is_none() && !arg.starts_with("--") => problem = Some(arg.clone()),
+                _ => return None,
+            }
+        }
+        options.problem = problem?;
+        Some(options)
+    }
+}
+
+/// Command line options for checking a proof
+#[derive(Default)]
+struct CheckOptions {
+    problem: String,
+    proof: String,
+    proof_format: Option<Format>,
+    core: Option<String>,
+}
+
+impl CheckOptions {
+    fn parse(args: &[String]) -> Option<Self> {
+        let mut options = Self::default();
+        let mut files = Vec::new();
+        let mut args = args.iter();
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--proof-format" => options.proof_format = Some(args.next()?.parse().ok()?),
+                "--core" => options.core = Some(args.next()?.clone()),
+                _ if files.len() < 2 && !arg.starts_with("--") => files.push(arg.clone()),
+                _ => return None,
+            }
+        }
+        match files.as_slice() {
+            [problem, proof] => {
+                options.problem = problem.clone();
+                options.proof = proof.clone();
+                Some(options)
+            }
+            _ => None,
+        }
+    }
+}
+
+fn main() {
+    if let Err(e) = run() {
+        eprintln!("Error: {}", e);
+        std::process::exit(1);
+    }
+}
+
+fn run() -> Result<(), Error> {
+    let usage = |executable: &str, usage: &str| {
+        Error::InvalidInput(format!("Usage: {} {}", executable, usage))
+    };
     match std::env::args().collect::<Vec<_>>().as_slice() {
-        [_, path] => {
-            let start = Instant::now();
-            let solution = Solver::parse_and_solve_file(path)?;
-            print_solution(solution);
+        [executable, command, args @ ..] if command == "check-proof" => {
+            match CheckOptions::parse(args) {
+                Some(options) => check_proof(options),
+                None => Err(usage(executable, CHECK_USAGE)),
+            }
+        }
+        [executable, command, args @ ..] if command == "mus" => match args {
+            [problem] => mus(problem),
+            _ => Err(usage(executable, MUS_USAGE)),
+        },
+        [executable, command, args @ ..] if command == "maxsat" => match args {
+            [problem] => maxsat(problem),
+            _ => Err(usage(executable, MAXSAT_USAGE)),
+        },
+        [executable, command, args @ ..] if command == "opb" => match args {
+            [problem] => pseudo_boolean(problem),
+            _ => Err(usage(executable, OPB_USAGE)),
+        },
+        [executable, command, args @ ..] if command == "icnf" => match args {
+            [problem] => incremental(problem),
+            _ => Err(usage(executable, ICNF_USAGE)),
+        },
+        [executable, command, args @ ..] if command == "qbf" => match args {
+            [problem] => quantified(problem),
+            _ => Err(usage(executable, QBF_USAGE)),
+        },
+        [executable, command, args @ ..] if command == "bmc" => match args {
+            [problem, bound] => match bound.parse() {
+                Ok(bound) => model_check(problem, bound),
+                Err(_) => Err(usage(executable, BMC_USAGE)),
+            },
+            _ => Err(usage(executable, BMC_USAGE)),
+        },
+        [executable, args @ ..] => match Options::parse(args) {
+            Some(options) => solve(options),
+            None => Err(usage(executable, USAGE)),
+        },
+        [] => unreachable!(),
+    }
+}
+
+fn solve(options: Options) -> Result<(), Error> {
+    let start = Instant::now();
+    let mut solver = Solver::new();
+    if let Some(path) = &options.proof {
+        let file = File::create(path).map_err(|e| file_error(path, e))?;
+        let format = options.proof_format.unwrap_or(Format::Drat);
+        solver.set_proof(Proof::with_format(file, format))?;
+    }
+    solver.set_verify(options.verify);
+    solver.set_track_core(options.core.is_some());
+
+    // The clauses only need to be kept around to write out a core
+    let cnf = if options.core.is_some() {
+        let cnf = dimacs::read(open(&options.problem)?, options.strict)?;
+        solver.add_cnf(&cnf)?;
+        Some(cnf)
+    } else {
+        dimacs::load(open(&options.problem)?, &mut solver, options.strict)?;
+        None
+    };
+
+    let solution = solver.solve()?;
+    if let (Some(path), Some(cnf), Some(core)) = (&options.core, &cnf, solver.unsat_core()) {
+        write_core(path, cnf, core)?;
+    }
+    print_solution(solution);
+    println!("c solved in {}ms", start.elapsed().as_millis());
+    Ok(())
+}
+
+fn check_proof(options: CheckOptions) -> Result<(), Error> {
+    let cnf = dimacs::read(open(&options.problem)?, false)?;
+    let proof = std::fs::read(&options.proof).map_err(|e| file_error(&options.proof, e))?;
+
+    let mut checker = Checker::new(&cnf);
+    if options.core.is_some() {
+        checker = checker.with_core();
+    }
+    if let Err(e) = checker.check(&proof, options.proof_format.unwrap_or(Format::Drat)) {
+        println!("s NOT VERIFIED");
+        return Err(e);
+    }
+    println!("s VERIFIED");
+
+    if let (Some(path), Some(core)) = (&options.core, checker.core()) {
+        write_core(path, &cnf, core)?;
+    }
+    Ok(())
+}
+
+/// Prints a minimal unsatisfiable subset of a problem's clauses as a new
+/// problem
+fn mus(problem: &str) -> Result<(), Error> {
+    let start = Instant::now();
+    let cnf = dimacs::read(open(problem)?, false)?;
+    match mus::extract(&cnf)? {
+        Some(mus) => {
+            println!(
+                "c MUS of {} out of {} clauses",
+                mus.len(),
+                cnf.clauses.len()
+            );
+            print!("c clauses");
+            for idx in mus.iter() {
+                print!(" {}", idx + 1);
+            }
+            println!();
             println!("c solved in {}ms", start.elapsed().as_millis());
+            subset(&cnf, mus).write(&mut std::io::stdout())
+        }
+        None => {
+            println!("s SATISFIABLE");
             Ok(())
         }
-        [executable] | [executable, ..] => Err(format!("Usage: {} <problem file>", executable)),
-        [] => unreachable!(),
     }
 }
 
+/// Prints an optimal assignment of a weighted MaxSAT problem, along with the
+/// cost of every better assignment found on the way to it
+fn maxsat(problem: &str) -> Result<(), Error> {
+    let start = Instant::now();
+    let wcnf = wcnf::read(open(problem)?)?;
+    match maxsat::solve(&wcnf, |cost| println!("o {}", cost))? {
+        Some(optimum) => {
+            println!("s OPTIMUM FOUND");
+            print!("v");
+            for (var, sign) in optimum.model {
+                print!(" {}{}", sign, var + 1);
+            }
+            println!(" 0");
+        }
+        None => println!("s UNSATISFIABLE"),
+    }
+    println!("c solved in {}ms", start.elapsed().as_millis());
+    Ok(())
+}
+
+/// Solves a pseudo-Boolean problem, printing the value of its objective (if
+/// it has one) under every better assignment found
+fn pseudo_boolean(problem: &str) -> Result<(), Error> {
+    let start = Instant::now();
+    let opb = opb::read(open(problem)?)?;
+    match opb::solve(&opb, |cost| println!("o {}", cost))? {
+        Some(model) => {
+            match opb.objective {
+                Some(_) => println!("s OPTIMUM FOUND"),
+                None => println!("s SATISFIABLE"),
+            }
+            print!("v");
+            for (var, sign) in model {
+                match sign {
+                    Sign::Positive => print!(" x{}", var + 1),
+                    Sign::Negative => print!(" -x{}", var + 1),
+                }
+            }
+            println!();
+        }
+        None => println!("s UNSATISFIABLE"),
+    }
+    println!("c solved in {}ms", start.elapsed().as_millis());
+    Ok(())
+}
+
+/// Answers each query of an incremental problem in turn, printing the
+/// assumptions responsible for every unsatisfiable one
+fn incremental(problem: &str) -> Result<(), Error> {
+    let start = Instant::now();
+    let icnf = icnf::read(open(problem)?)?;
+    let mut num_queries = 0;
+    icnf::solve(&icnf, |solution, failed| {
+        num_queries += 1;
+        let unsat = matches!(solution, Solution::Unsat);
+        print_solution(solution);
+        if unsat {
+            print!("u");
+            for literal in failed {
+                print!(" {}", isize::from(*literal));
+            }
+            println!(" 0");
+        }
+    })?;
+    println!(
+        "c solved {} queries in {}ms",
+        num_queries,
+        start.elapsed().as_millis()
+    );
+    Ok(())
+}
+
+/// Decides a quantified Boolean formula, printing a winning assignment to its
+/// outermost block if there is one
+fn quantified(problem: &str) -> Result<(), Error> {
+    let start = Instant::now();
+    let qdimacs = qdimacs::read(open(problem)?)?;
+    let answer = qbf::solve(&qdimacs)?;
+    match answer.value {
+        true => println!("s TRUE"),
+        false => println!("s FALSE"),
+    }
+    if let Some(certificate) = answer.certificate {
+        print!("v");
+        for (var, sign) in certificate {
+            print!(" {}{}", sign, var + 1);
+        }
+        println!(" 0");
+    }
+    println!("c solved in {}ms", start.elapsed().as_millis());
+    Ok(())
+}
+
+/// Looks for a trace of a circuit reaching a bad state within `bound` steps,
+/// printing a `u` line for every number of steps found to be safe and then
+/// the trace as an AIGER witness, or an unknown result if there isn't one
+fn model_check(problem: &str, bound: usize) -> Result<(), Error> {
+    let start = Instant::now();
+    let aiger = aiger::read(open(problem)?)?;
+    match bmc::check(&aiger, bound, |depth| println!("u{}", depth))? {
+        Some(witness) => {
+            let bits = |values: &[bool]| {
+                values
+                    .iter()
+                    .map(|value| if *value { '1' } else { '0' })
+                    .collect::<String>()
+            };
+            println!("1");
+            println!("b{}", witness.property);
+            println!("{}", bits(&witness.latches));
+            for inputs in witness.inputs.iter() {
+                println!("{}", bits(inputs));
+            }
+        }
+        None => {
+            println!("2");
+            let properties = match aiger.bad.len() {
+                0 => aiger.outputs.len(),
+                len => len,
+            };
+            println!(
+                "{}",
+                (0..properties)
+                    .map(|idx| format!("b{}", idx))
+                    .collect::<String>()
+            );
+        }
+    }
+    println!(".");
+    println!("c solved in {}ms", start.elapsed().as_millis());
+    Ok(())
+}
+
+/// Writes the clauses of a problem with the given indices as a new problem
+fn write_core(path: &str, cnf: &dimacs::Cnf, core: Vec<usize>) -> Result<(), Error> {
+    let mut file = File::create(path).map_err(|e| file_error(path, e))?;
+    subset(cnf, core).write(&mut file)
+}
+
+/// The clauses of a problem with the given indices
+fn subset(cnf: &dimacs::Cnf, indices: Vec<usize>) -> dimacs::Cnf {
+    dimacs::Cnf {
+        num_variables: cnf.num_variables,
+        clauses: indices
+            .into_iter()
+            .map(|idx| cnf.clauses[idx].clone())
+            .collect(),
+        xors: Vec::new(),
+    }
+}
+
+/// Opens a problem file, or standard input if the path is `-`
+fn open(path: &str) -> Result<Box<dyn Read>, Error> {
+    if path == "-" {
+        return Ok(Box::new(std::io::stdin()));
+    }
+    let file = File::open(path).map_err(|e| file_error(path, e))?;
+    Ok(Box::new(file))
+}
+
+/// An error reading or writing `path`, with the path included in its message
+fn file_error(path: &str, e: std::io::Error) -> Error {
+    Error::Io(std::io::Error::new(e.kind(), format!("{}: {}", path, e)))
+}
+
 fn print_solution(solution: Solution<impl IntoIterator<Item = (Variable, Sign)>>) {
     match solution {
         Solution::Unsat => println!("s UNSATISFIABLE"),
+        Solution::Unknown => println!("s UNKNOWN"),
         Solution::Sat(assignment) => {
             println!("s SATISFIABLE");
             print!("v");
             for (var, sign) in assignment {
-                print!(" {}{}", sign, var);
+                print!(" {}{}", sign, var + 1);
             }
             println!(" 0");
         }
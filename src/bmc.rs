@@ -0,0 +1,324 @@
+use crate::{aiger::Aiger, Error, Literal, Solution, Solver, Variable};
+
+/// A trace of a circuit that ends in a bad state
+#[derive(Clone, Debug, PartialEq)]
+pub struct Witness {
+    /// The index of the bad-state property that the last step violates
+    pub property: usize,
+    /// The value of each latch in the initial state
+    pub latches: Vec<bool>,
+    /// The value of each input at each step
+    pub inputs: Vec<Vec<bool>>,
+}
+
+/// Looks for a trace of at most `bound` steps after the initial state that
+/// reaches a bad state, while every invariant constraint holds along the
+/// way. Circuits without any bad-state properties have their outputs taken
+/// as bad, as in earlier model checking competitions. The transition
+/// relation is unrolled into one solver a step at a time, so what it learns
+/// about earlier steps carries over, and `checked` is called with each
+/// number of steps found to be safe.
+pub fn check(
+    aiger: &Aiger,
+    bound: usize,
+    mut checked: impl FnMut(usize),
+) -> Result<Option<Witness>, Error> {
+    let bad = match aiger.bad.is_empty() {
+        true => &aiger.outputs,
+        false => &aiger.bad,
+    };
+    let mut solver = Solver::new();
+    let truth = Literal::new(solver.new_variable(), true);
+    solver.add_clause(&[truth])?;
+
+    let mut frames = Vec::<Frame>::new();
+    for depth in 0..=bound {
+        let frame = Frame::unroll(aiger, frames.last(), truth, &mut solver)?;
+        for constraint in aiger.constraints.iter() {
+            solver.add_clause(&[frame.literal(*constraint)?])?;
+        }
+
+        // Only the bad states of this step are looked for, and they're ruled
+        // out again once none is reachable
+        let activation = Literal::new(solver.new_variable(), true);
+        let mut clause = bad
+            .iter()
+            .map(|literal| frame.literal(*literal))
+            .collect::<Result<Vec<_>, _>>()?;
+        clause.push(!activation);
+        solver.add_clause(&clause)?;
+        frames.push(frame);
+
+        let model = match solver.solve_with_assumptions(&[activation])? {
+            Solution::Sat(model) => model,
+            Solution::Unsat => {
+                solver.add_clause(&[!activation])?;
+                checked(depth);
+                continue;
+            }
+            Solution::Unknown => return Err(Error::ResourceLimit),
+        };
+        let value = |frame: &Frame, literal: usize| -> Result<bool, Error> {
+            let literal = frame.literal(literal)?;
+            let (value, sign): (bool, bool) =
+                (model[literal.var()].1.into(), literal.sign().into());
+            Ok(value == sign)
+        };
+        let property = bad
+            .iter()
+            .position(|literal| matches!(value(&frames[depth], *literal), Ok(true)))
+            .ok_or_else(|| Error::Internal("no bad state in a witness".to_owned()))?;
+        return Ok(Some(Witness {
+            property,
+            latches: aiger
+                .latches
+                .iter()
+                .map(|latch| value(&frames[0], latch.literal))
+                .collect::<Result<_, _>>()?,
+            inputs: frames
+                .iter()
+                .map(|frame| {
+                    aiger
+                        .inputs
+                        .iter()
+                        .map(|input| value(frame, *input))
+                        .collect::<Result<_, _>>()
+                })
+                .collect::<Result<_, _>>()?,
+        }));
+    }
+    Ok(None)
+}
+
+/// The literals standing for the variables of a circuit at one step
+struct Frame {
+    /// The literal of each variable, if it's defined
+    literals: Vec<Option<Literal>>,
+}
+
+impl Frame {
+    /// Adds the variables of the step after `previous` to `solver`, or of
+    /// the initial state if there is no previous step, along with the
+    /// Tseitin encoding of the gates between them
+    fn unroll(
+        aiger: &Aiger,
+        previous: Option<&Frame>,
+        truth: Literal,
+        solver: &mut Solver,
+    ) -> Result<Frame, Error> {
+        // Room is only made for the variables that are defined, which may be
+        // far fewer than the header declares
+        let defined = aiger
+            .inputs
+            .iter()
+            .chain(aiger.latches.iter().map(|latch| &latch.literal))
+            .chain(aiger.ands.iter().map(|(output, _, _)| output));
+        let mut frame = Frame {
+            literals: vec![None; defined.map(|literal| literal / 2 + 1).max().unwrap_or(1)],
+        };
+        let mut fresh = || Literal::new(solver.new_variable(), true);
+        frame.literals[0] = Some(!truth);
+        for input in aiger.inputs.iter() {
+            frame.literals[input / 2] = Some(fresh());
+        }
+        for latch in aiger.latches.iter() {
+            frame.literals[latch.literal / 2] = Some(match (previous, latch.reset) {
+                (Some(previous), _) => previous.literal(latch.next)?,
+                (None, Some(true)) => truth,
+                (None, Some(false)) => !truth,
+                (None, None) => fresh(),
+            });
+        }
+        for (output, _, _) in aiger.ands.iter() {
+            frame.literals[output / 2] = Some(fresh());
+        }
+
+        for (output, left, right) in aiger.ands.iter() {
+            let output = frame.literal(*output)?;
+            let (left, right) = (frame.literal(*left)?, frame.literal(*right)?);
+            solver.add_clause(&[!output, left])?;
+            solver.add_clause(&[!output, right])?;
+            solver.add_clause(&[output, !left, !right])?;
+        }
+        Ok(frame)
+    }
+
+    /// The literal standing for an AIGER literal
+    fn literal(&self, literal: usize) -> Result<Literal, Error> {
+        let var: Variable = literal / 2;
+        match self.literals.get(var).copied().flatten() {
+            Some(positive) if literal % 2 == 1 => Ok(!positive),
+            Some(positive) => Ok(positive),
+            None => Err(Error::InvalidInput(format!(
+                "AIGER literal {} refers to a variable that is never defined",
+                literal
+            ))),
+        }
+    }
+}
+
+#[test]
+fn counter() -> Result<(), Error> {
+    // A two-bit counter that's bad once both bits are set, which takes three
+    // steps
+    let aiger =
+        crate::aiger::parse(b"aag 6 0 2 0 4 1\n2 3\n4 11\n12\n6 2 5\n8 3 4\n10 7 9\n12 2 4\n")?;
+    let mut safe = Vec::new();
+    assert_eq!(check(&aiger, 2, |depth| safe.push(depth))?, None);
+    assert_eq!(safe, vec![0, 1, 2]);
+    assert_eq!(
+        check(&aiger, 10, |_| {})?,
+        Some(Witness {
+            property: 0,
+            latches: vec![false, false],
+            inputs: vec![vec![]; 4],
+        })
+    );
+
+    // An output that's just an input is bad straight away, however many
+    // variables the header declares
+    let aiger = crate::aiger::parse(b"aag 4611686018427387903 1 0 1 0\n2\n2\n")?;
+    assert_eq!(
+        check(&aiger, 0, |_| {})?,
+        Some(Witness {
+            property: 0,
+            latches: vec![],
+            inputs: vec![vec![true]],
+        })
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+impl quickcheck::Arbitrary for Aiger {
+    /// A circuit small enough that every trace of it can be simulated
+    fn arbitrary<G: quickcheck::Gen>(g: &mut G) -> Self {
+        use crate::aiger::Latch;
+        use rand::Rng;
+
+        let (num_inputs, num_latches) = (g.gen_range(0, 3), g.gen_range(1, 4));
+        let num_ands = g.gen_range(1, 8);
+        let max_variable = num_inputs + num_latches + num_ands;
+        let mut random = |below: usize| g.gen_range(0, 2 * below + 2);
+        let ands = (0..num_ands)
+            .map(|idx| {
+                let output = 2 * (num_inputs + num_latches + idx + 1);
+                (output, random(output / 2 - 1), random(output / 2 - 1))
+            })
+            .collect::<Vec<_>>();
+        let latches = (0..num_latches)
+            .map(|idx| {
+                let literal = 2 * (num_inputs + idx + 1);
+                Latch {
+                    literal,
+                    next: random(max_variable),
+                    reset: match random(1) {
+                        0 => None,
+                        reset => Some(reset == 1),
+                    },
+                }
+            })
+            .collect();
+        Aiger {
+            max_variable,
+            inputs: (1..=num_inputs).map(|var| 2 * var).collect(),
+            latches,
+            outputs: Vec::new(),
+            bad: vec![random(max_variable)],
+            constraints: match random(1) {
+                0 => vec![random(max_variable)],
+                _ => Vec::new(),
+            },
+            ands,
+        }
+    }
+}
+
+#[cfg(test)]
+#[quickcheck_macros::quickcheck]
+fn matches_simulation(aiger: Aiger) -> Result<(), Error> {
+    use std::collections::HashSet;
+
+    // The value of every variable given the latches and inputs
+    fn simulate(aiger: &Aiger, latches: &[bool], inputs: &[bool]) -> Vec<bool> {
+        let mut values = vec![false; aiger.max_variable + 1];
+        for (latch, value) in aiger.latches.iter().zip(latches) {
+            values[latch.literal / 2] = *value;
+        }
+        for (input, value) in aiger.inputs.iter().zip(inputs) {
+            values[input / 2] = *value;
+        }
+        for (output, left, right) in aiger.ands.iter() {
+            let value = |literal: usize| values[literal / 2] != (literal % 2 == 1);
+            values[output / 2] = value(*left) && value(*right);
+        }
+        values
+    }
+    let value = |values: &[bool], literal: usize| values[literal / 2] != (literal % 2 == 1);
+    let bits = |bits: u32, len: usize| (0..len).map(|idx| bits >> idx & 1 == 1).collect::<Vec<_>>();
+
+    let (num_inputs, num_latches) = (aiger.inputs.len(), aiger.latches.len());
+
+    // Step through every reachable state to find the first step at which
+    // a bad state can be reached
+    let bound = 5;
+    let allowed = |values: &[bool]| aiger.constraints.iter().all(|c| value(values, *c));
+    let mut states = (0..1u32 << num_latches)
+        .map(|state| bits(state, num_latches))
+        .filter(|state| {
+            let mut latches = aiger.latches.iter().zip(state.iter());
+            latches.all(|(latch, value)| latch.reset.is_none_or(|reset| reset == *value))
+        })
+        .collect::<HashSet<_>>();
+    let mut expected = None;
+    for depth in 0..=bound {
+        let mut next = HashSet::new();
+        for state in states.iter() {
+            for inputs in 0..1u32 << num_inputs {
+                let values = simulate(&aiger, state, &bits(inputs, num_inputs));
+                if !allowed(&values) {
+                    continue;
+                }
+                if value(&values, aiger.bad[0]) {
+                    expected = expected.or(Some(depth));
+                }
+                next.insert(
+                    aiger
+                        .latches
+                        .iter()
+                        .map(|latch| value(&values, latch.next))
+                        .collect::<Vec<_>>(),
+                );
+            }
+        }
+        if expected.is_some() {
+            break;
+        }
+        states = next;
+    }
+
+    let witness = check(&aiger, bound, |_| {})?;
+    assert_eq!(
+        witness.as_ref().map(|witness| witness.inputs.len() - 1),
+        expected
+    );
+
+    // Replaying the witness reaches the bad state, never breaking a
+    // constraint on the way
+    if let Some(witness) = witness {
+        let mut state = witness.latches.clone();
+        for (step, inputs) in witness.inputs.iter().enumerate() {
+            let values = simulate(&aiger, &state, inputs);
+            assert!(allowed(&values));
+            if step + 1 == witness.inputs.len() {
+                assert!(value(&values, aiger.bad[0]));
+            }
+            state = aiger
+                .latches
+                .iter()
+                .map(|latch| value(&values, latch.next))
+                .collect();
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,95 @@
+use std::time::Duration;
+
+/// Counts of the work done by a solver
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct Statistics {
+    pub conflicts: usize,
+    pub decisions: usize,
+    pub propagations: usize,
+}
+
+impl std::ops::Sub for Statistics {
+    type Output = Self;
+
+    fn sub(self, other: Self) -> Self::Output {
+        Self {
+            conflicts: self.conflicts - other.conflicts,
+            decisions: self.decisions - other.decisions,
+            propagations: self.propagations - other.propagations,
+        }
+    }
+}
+
+/// Limits on the work a single solve call may do before giving up and
+/// returning `Solution::Unknown`. Every limit is unset by default.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Budget {
+    conflicts: Option<usize>,
+    decisions: Option<usize>,
+    propagations: Option<usize>,
+    time: Option<Duration>,
+}
+
+impl Budget {
+    /// A budget with no limits
+    pub fn unlimited() -> Self {
+        Self::default()
+    }
+
+    pub fn conflicts(mut self, conflicts: usize) -> Self {
+        self.conflicts = Some(conflicts);
+        self
+    }
+
+    pub fn decisions(mut self, decisions: usize) -> Self {
+        self.decisions = Some(decisions);
+        self
+    }
+
+    pub fn propagations(mut self, propagations: usize) -> Self {
+        self.propagations = Some(propagations);
+        self
+    }
+
+    pub fn time(mut self, time: Duration) -> Self {
+        self.time = Some(time);
+        self
+    }
+
+    /// Whether any limit has been reached, given the work done and time
+    /// taken so far
+    pub(crate) fn exhausted(&self, spent: Statistics, elapsed: impl FnOnce() -> Duration) -> bool {
+        let reached = |limit: Option<usize>, spent| limit.is_some_and(|limit| spent >= limit);
+        reached(self.conflicts, spent.conflicts)
+            || reached(self.decisions, spent.decisions)
+            || reached(self.propagations, spent.propagations)
+            || self.time.is_some_and(|time| elapsed() >= time)
+    }
+}
+
+#[test]
+fn exhausted() {
+    let spent = Statistics {
+        conflicts: 10,
+        decisions: 20,
+        propagations: 30,
+    };
+    let elapsed = || Duration::from_secs(1);
+
+    assert!(!Budget::unlimited().exhausted(spent, elapsed));
+    assert!(Budget::unlimited().conflicts(10).exhausted(spent, elapsed));
+    assert!(Budget::unlimited().decisions(5).exhausted(spent, elapsed));
+    assert!(Budget::unlimited()
+        .conflicts(100)
+        .propagations(30)
+        .exhausted(spent, elapsed));
+    assert!(Budget::unlimited()
+        .time(Duration::from_millis(10))
+        .exhausted(spent, elapsed));
+    assert!(!Budget::unlimited()
+        .conflicts(11)
+        .decisions(21)
+        .propagations(31)
+        .time(Duration::from_secs(2))
+        .exhausted(spent, elapsed));
+}
@@ -1,4 +1,4 @@
-use crate::{formula::Clause, Assignments, DecisionLevel, Evaluate, Literal, Variable};
+use crate::{formula::Clause, Assignments, DecisionLevel, Error, Evaluate, Literal, Variable};
 use fixedbitset::FixedBitSet;
 
 pub struct Conflict {
@@ -16,6 +16,10 @@ impl Conflict {
         }
     }
 
+    pub fn grow(&mut self, num_vars: Variable) {
+        self.literals.grow(num_vars * 2);
+    }
+
     pub fn assigned_at_level(&self) -> Variable {
         self.assigned_at_level
     }
@@ -56,14 +60,12 @@ impl Conflict {
                     self.add(*literal, assignments);
                 }
             }
+            Clause::Pb { .. } => {
+                for literal in clause.reason(None, assignments) {
+                    self.add(literal, assignments);
+                }
+            }
         };
-
-        // Ensure there is at least one literal assigned at the conflict level
-        debug_assert_ne!(
-            self.assigned_at_level,
-            0,
-            "There should be at least one literal assigned at the conflict level in the conflict clause"
-        );
     }
 
     pub fn resolve(
@@ -71,7 +73,7 @@ impl Conflict {
         literal: Literal,
         other: &Clause,
         assignments: &Assignments,
-    ) -> Result<(), String> {
+    ) -> Result<(), Error> {
         let code = literal.code();
 
         debug_assert!(self.literals[code]);
@@ -86,7 +88,16 @@ impl Conflict {
                     self.add(*literal, assignments);
                 }
             }
-            _ => return Err("'antecedent' clause wasn't actually antecedent".to_string()),
+            Clause::Pb { .. } => {
+                for literal in other.reason(Some(!literal), assignments) {
+                    self.add(literal, assignments);
+                }
+            }
+            _ => {
+                return Err(Error::Internal(
+                    "'antecedent' clause wasn't actually antecedent".to_string(),
+                ))
+            }
         }
 
         Ok(())
@@ -185,6 +185,16 @@ where
         })
     }
 
+    /// Adds `count` new items with no priority, making them available to pop
+    pub fn grow(&mut self, count: usize) {
+        for _ in 0..count {
+            let item = self.priorities.len();
+            self.priorities.push(Default::default());
+            self.positions.push(None);
+            self.add_to_heap(item.into());
+        }
+    }
+
     pub fn add_to_heap(&mut self, item: T) {
         let item: usize = item.into();
         if self.positions[item].is_some() {
@@ -0,0 +1,793 @@
+use crate::{Error, Literal, Solver, Variable};
+use bzip2::read::MultiBzDecoder;
+use flate2::read::MultiGzDecoder;
+use std::{
+    fmt::{self, Display, Formatter},
+    fs::File,
+    io::{BufRead, BufReader, Cursor, Read, Write},
+    path::Path,
+};
+use xz2::read::XzDecoder;
+
+/// The most clauses to make room for on the word of a header alone
+const MAX_RESERVED_CLAUSES: usize = 1 << 20;
+
+/// The largest variable accepted from formats whose headers don't declare how
+/// many there are, so that a stray number can't make the solver make room for
+/// trillions of them
+pub(crate) const MAX_VARIABLE: usize = 1 << 26;
+
+/// A problem in conjunctive normal form, as read from a DIMACS file
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Cnf {
+    pub num_variables: usize,
+    pub clauses: Vec<Vec<Literal>>,
+    /// XOR constraints, each satisfied when an odd number of its literals
+    /// are true
+    pub xors: Vec<Vec<Literal>>,
+}
+
+impl Cnf {
+    /// Writes the problem in DIMACS format
+    pub fn write(&self, out: &mut impl Write) -> Result<(), Error> {
+        let mut write = || -> std::io::Result<()> {
+            let num_clauses = self.clauses.len() + self.xors.len();
+            writeln!(out, "p cnf {} {}", self.num_variables, num_clauses)?;
+            for xor in self.xors.iter() {
+                write!(out, "x")?;
+                for literal in xor {
+                    write!(out, "{} ", isize::from(*literal))?;
+                }
+                writeln!(out, "0")?;
+            }
+            for clause in self.clauses.iter() {
+                for literal in clause {
+                    write!(out, "{} ", isize::from(*literal))?;
+                }
+                writeln!(out, "0")?;
+            }
+            out.flush()
+        };
+        write().map_err(Error::Io)
+    }
+}
+
+pub fn parse_file(path: impl AsRef<Path>) -> Result<Cnf, Error> {
+    read_file(path.as_ref(), false)
+}
+
+/// Like `parse_file`, but held to the rules of `parse_strict`
+pub fn parse_file_strict(path: impl AsRef<Path>) -> Result<Cnf, Error> {
+    read_file(path.as_ref(), true)
+}
+
+fn read_file(path: &Path, strict: bool) -> Result<Cnf, Error> {
+    read(open(path)?, strict)
+}
+
+/// Reads a problem in DIMACS CNF format, which has to follow the rules of
+/// `parse`, or those of `parse_strict` if `strict` is set. Problems
+/// compressed with gzip, bzip2 or xz are decompressed as they're read.
+pub fn read(reader: impl Read, strict: bool) -> Result<Cnf, Error> {
+    let mut cnf = Cnf::default();
+    stream(decompress(reader)?, &mut cnf, strict)?;
+    Ok(cnf)
+}
+
+/// Like `read`, but puts the problem straight into a solver, without keeping
+/// its clauses around in between
+pub fn load(reader: impl Read, solver: &mut Solver, strict: bool) -> Result<(), Error> {
+    stream(decompress(reader)?, solver, strict)
+}
+
+/// Wraps `reader` in a decoder if it reads something compressed with gzip,
+/// bzip2 or xz, going by the magic bytes at the start
+pub(crate) fn decompress<'a>(mut reader: impl Read + 'a) -> Result<Box<dyn BufRead + 'a>, Error> {
+    let mut magic = Vec::with_capacity(6);
+    reader.by_ref().take(6).read_to_end(&mut magic)?;
+    let is = |prefix: &[u8]| magic.starts_with(prefix);
+    let (gzip, bzip2, xz) = (is(&[0x1f, 0x8b]), is(b"BZh"), is(b"\xfd7zXZ\x00"));
+
+    let reader = Cursor::new(magic).chain(reader);
+    Ok(if gzip {
+        Box::new(BufReader::new(MultiGzDecoder::new(reader)))
+    } else if bzip2 {
+        Box::new(BufReader::new(MultiBzDecoder::new(reader)))
+    } else if xz {
+        Box::new(BufReader::new(XzDecoder::new_multi_decoder(reader)))
+    } else {
+        Box::new(BufReader::new(reader))
+    })
+}
+
+fn stream(mut reader: impl BufRead, sink: &mut impl Sink, strict: bool) -> Result<(), Error> {
+    let mut parser = Parser::new(strict);
+    let mut line = Vec::new();
+    while !parser.done && reader.read_until(b'\n', &mut line)? > 0 {
+        parser.parse_line(&line, sink)?;
+        line.clear();
+    }
+    parser.finish(sink)
+}
+
+/// Parses a problem in DIMACS CNF format. Comments and empty lines may appear
+/// anywhere, a clause may span several lines and a line may hold several
+/// clauses. Anything after a line containing just `%`, which ends the
+/// problems of the SATLIB benchmarks, is ignored, and the last clause doesn't
+/// need to be terminated by a 0. A clause starting with `x`, as in the input
+/// of CryptoMiniSat, is an XOR constraint rather than a disjunction.
+pub fn parse(lines: impl IntoIterator<Item = impl AsRef<str>>) -> Result<Cnf, Error> {
+    let mut cnf = Cnf::default();
+    parse_into(lines, &mut cnf, false)?;
+    Ok(cnf)
+}
+
+/// Like `parse`, but also rejects problems whose last clause isn't
+/// terminated, or whose header doesn't give their exact number of clauses and
+/// largest variable
+pub fn parse_strict(lines: impl IntoIterator<Item = impl AsRef<str>>) -> Result<Cnf, Error> {
+    let mut cnf = Cnf::default();
+    parse_into(lines, &mut cnf, true)?;
+    Ok(cnf)
+}
+
+pub(crate) fn parse_into(
+    lines: impl IntoIterator<Item = impl AsRef<str>>,
+    sink: &mut impl Sink,
+    strict: bool,
+) -> Result<(), Error> {
+    let mut parser = Parser::new(strict);
+    for line in lines {
+        if parser.done {
+            break;
+        }
+        parser.parse_line(line.as_ref().as_bytes(), sink)?;
+    }
+    parser.finish(sink)
+}
+
+/// Somewhere for the parser to put a problem as it's read
+pub(crate) trait Sink {
+    fn header(&mut self, num_variables: usize, num_clauses: usize);
+
+    /// Takes a complete clause. The parser clears it afterwards.
+    fn clause(&mut self, clause: &mut Vec<Literal>) -> Result<(), Error>;
+
+    /// Takes a complete XOR constraint, as for `clause`
+    fn xor(&mut self, literals: &mut Vec<Literal>) -> Result<(), Error>;
+}
+
+impl Sink for Cnf {
+    fn header(&mut self, num_variables: usize, num_clauses: usize) {
+        self.num_variables = num_variables;
+        self.clauses.reserve(num_clauses.min(MAX_RESERVED_CLAUSES));
+    }
+
+    fn clause(&mut self, clause: &mut Vec<Literal>) -> Result<(), Error> {
+        self.clauses.push(std::mem::take(clause));
+        Ok(())
+    }
+
+    fn xor(&mut self, literals: &mut Vec<Literal>) -> Result<(), Error> {
+        self.xors.push(std::mem::take(literals));
+        Ok(())
+    }
+}
+
+impl Sink for Solver {
+    fn header(&mut self, num_variables: usize, _: usize) {
+        if num_variables > self.num_variables() {
+            self.add_variables(num_variables - self.num_variables());
+        }
+    }
+
+    fn clause(&mut self, clause: &mut Vec<Literal>) -> Result<(), Error> {
+        self.add_clause(clause)
+    }
+
+    fn xor(&mut self, literals: &mut Vec<Literal>) -> Result<(), Error> {
+        self.add_xor(literals)
+    }
+}
+
+/// Parses a problem a line at a time, handing each clause on as soon as it's
+/// complete
+struct Parser {
+    strict: bool,
+    /// The number of the last line parsed
+    line: usize,
+    header: Option<Header>,
+    clause: Vec<Literal>,
+    /// Whether the clause being parsed started with `x`, making it an XOR
+    /// constraint
+    xor: bool,
+    num_clauses: usize,
+    max_variable: usize,
+    /// Whether a `%` line has ended the problem
+    done: bool,
+}
+
+impl Parser {
+    fn new(strict: bool) -> Self {
+        Self {
+            strict,
+            line: 0,
+            header: None,
+            clause: Vec::new(),
+            xor: false,
+            num_clauses: 0,
+            max_variable: 0,
+            done: false,
+        }
+    }
+
+    fn parse_line(&mut self, line: &[u8], sink: &mut impl Sink) -> Result<(), Error> {
+        self.line += 1;
+        let mut pos = skip_whitespace(line, 0);
+        match line.get(pos) {
+            None | Some(b'c') => return Ok(()),
+            Some(b'%') if skip_whitespace(line, pos + 1) == line.len() => {
+                self.done = true;
+                return Ok(());
+            }
+            _ => {}
+        }
+        // Larger variables can't be told apart from their literals' signs
+        let num_variables = match &self.header {
+            Some(header) => header.num_variables.min((Variable::MAX >> 1) - 1),
+            None => {
+                let header = parse_header(self.line, &String::from_utf8_lossy(line))?;
+                sink.header(header.num_variables, header.num_clauses);
+                self.header = Some(header);
+                return Ok(());
+            }
+        };
+
+        while pos < line.len() {
+            if line[pos] == b'x' && self.clause.is_empty() && !self.xor {
+                self.xor = true;
+                pos = skip_whitespace(line, pos + 1);
+                continue;
+            }
+            let start = pos;
+            let negative = line[pos] == b'-';
+            if negative || line[pos] == b'+' {
+                pos += 1;
+            }
+            let digits = pos;
+            let mut var: usize = 0;
+            while pos < line.len() && line[pos].is_ascii_digit() {
+                var = var
+                    .saturating_mul(10)
+                    .saturating_add((line[pos] - b'0') as usize);
+                pos += 1;
+            }
+            if pos == digits || matches!(line.get(pos), Some(b) if !b.is_ascii_whitespace()) {
+                return Err(self
+                    .error(ParseErrorKind::InvalidLiteral, line, start)
+                    .into());
+            }
+
+            if var == 0 {
+                self.end_clause(sink)?;
+            } else if var > num_variables {
+                let kind = ParseErrorKind::VariableOutOfRange(num_variables);
+                return Err(self.error(kind, line, start).into());
+            } else {
+                self.max_variable = self.max_variable.max(var);
+                self.clause.push(Literal::new(var - 1, !negative));
+            }
+            pos = skip_whitespace(line, pos);
+        }
+        Ok(())
+    }
+
+    fn finish(mut self, sink: &mut impl Sink) -> Result<(), Error> {
+        let end = ParseError::at_end(ParseErrorKind::MissingHeader, self.line + 1);
+        let header = self.header.take().ok_or_else(|| end.clone())?;
+        if !self.clause.is_empty() || self.xor {
+            if self.strict {
+                let kind = ParseErrorKind::UnterminatedClause;
+                return Err(ParseError { kind, ..end }.into());
+            }
+            self.end_clause(sink)?;
+        }
+
+        if self.strict {
+            if self.num_clauses != header.num_clauses {
+                let kind = ParseErrorKind::ClauseCountMismatch(self.num_clauses);
+                return Err(header
+                    .error(kind, header.num_clauses, header.columns.1)
+                    .into());
+            }
+            if self.max_variable != header.num_variables {
+                let kind = ParseErrorKind::VariableCountMismatch(self.max_variable);
+                return Err(header
+                    .error(kind, header.num_variables, header.columns.0)
+                    .into());
+            }
+        }
+        Ok(())
+    }
+
+    fn end_clause(&mut self, sink: &mut impl Sink) -> Result<(), Error> {
+        if std::mem::take(&mut self.xor) {
+            sink.xor(&mut self.clause)?;
+        } else {
+            sink.clause(&mut self.clause)?;
+        }
+        self.clause.clear();
+        self.num_clauses += 1;
+        Ok(())
+    }
+
+    /// An error about the token starting at byte `start` of the current line
+    fn error(&self, kind: ParseErrorKind, line: &[u8], start: usize) -> ParseError {
+        let end = line[start..]
+            .iter()
+            .position(u8::is_ascii_whitespace)
+            .map_or(line.len(), |len| start + len);
+        ParseError {
+            kind,
+            line: self.line,
+            // Count characters rather than bytes, skipping UTF-8 continuation
+            // bytes
+            column: line[..start].iter().filter(|b| *b & 0xc0 != 0x80).count() + 1,
+            token: String::from_utf8_lossy(&line[start..end]).into_owned(),
+        }
+    }
+}
+
+fn skip_whitespace(line: &[u8], mut pos: usize) -> usize {
+    while pos < line.len() && line[pos].is_ascii_whitespace() {
+        pos += 1;
+    }
+    pos
+}
+
+/// The counts given by a `p cnf <variables> <clauses>` line
+pub(crate) struct Header {
+    line: usize,
+    pub(crate) num_variables: usize,
+    num_clauses: usize,
+    /// The columns the two counts start at
+    columns: (usize, usize),
+}
+
+impl Header {
+    /// An error pointing at one of the counts
+    fn error(&self, kind: ParseErrorKind, count: usize, column: usize) -> ParseError {
+        ParseError {
+            kind,
+            line: self.line,
+            column,
+            token: count.to_string(),
+        }
+    }
+}
+
+pub(crate) fn parse_header(number: usize, line: &str) -> Result<Header, ParseError> {
+    let tokens = tokens(line);
+    let error = |kind, idx: usize| match tokens.get(idx) {
+        Some((column, token)) => ParseError {
+            kind,
+            line: number,
+            column: *column,
+            token: (*token).to_owned(),
+        },
+        None => ParseError {
+            column: line.chars().count() + 1,
+            ..ParseError::at_end(kind, number)
+        },
+    };
+
+    if tokens.first().map(|(_, token)| *token) != Some("p") {
+        return Err(error(ParseErrorKind::MissingHeader, 0));
+    }
+    if tokens.get(1).map(|(_, token)| *token) != Some("cnf") {
+        return Err(error(ParseErrorKind::MalformedHeader, 1));
+    }
+    let count = |idx: usize| -> Result<(usize, usize), ParseError> {
+        tokens
+            .get(idx)
+            .and_then(|(column, token)| Some((token.parse().ok()?, *column)))
+            .ok_or_else(|| error(ParseErrorKind::MalformedHeader, idx))
+    };
+    let ((num_variables, variables_column), (num_clauses, clauses_column)) = (count(2)?, count(3)?);
+    if tokens.len() > 4 {
+        return Err(error(ParseErrorKind::MalformedHeader, 4));
+    }
+    Ok(Header {
+        line: number,
+        num_variables,
+        num_clauses,
+        columns: (variables_column, clauses_column),
+    })
+}
+
+/// The whitespace-separated tokens of a line, along with the columns they
+/// start at
+pub(crate) fn tokens(line: &str) -> Vec<(usize, &str)> {
+    let mut tokens = Vec::new();
+    let mut start = None;
+    let chars = line
+        .char_indices()
+        .chain(std::iter::once((line.len(), ' ')));
+    for (column, (offset, c)) in chars.enumerate() {
+        match (start, c.is_whitespace()) {
+            (None, false) => start = Some((column + 1, offset)),
+            (Some((column, begin)), true) => {
+                tokens.push((column, &line[begin..offset]));
+                start = None;
+            }
+            _ => {}
+        }
+    }
+    tokens
+}
+
+/// The ways in which a problem file can fail to be valid DIMACS
+#[derive(Clone, Debug, PartialEq)]
+pub enum ParseErrorKind {
+    /// The first line that isn't a comment isn't a `p cnf` header
+    MissingHeader,
+    /// The header isn't of the form `p cnf <variables> <clauses>`
+    MalformedHeader,
+    /// A clause contains something other than an integer
+    InvalidLiteral,
+    /// A mistake particular to WCNF
+    Wcnf(crate::wcnf::ErrorKind),
+    /// A mistake particular to OPB
+    Opb(crate::opb::ErrorKind),
+    /// A mistake particular to iCNF
+    Icnf(crate::icnf::ErrorKind),
+    /// A mistake particular to AIGER
+    Aiger(crate::aiger::ErrorKind),
+    /// A mistake particular to QDIMACS
+    Qdimacs(crate::qdimacs::ErrorKind),
+    /// A literal's variable is greater than the number of variables declared
+    /// in the header, which is given
+    VariableOutOfRange(usize),
+    /// The last clause isn't terminated by a 0
+    UnterminatedClause,
+    /// The number of clauses isn't the one declared in the header. The
+    /// actual number is given.
+    ClauseCountMismatch(usize),
+    /// The largest variable isn't the number of variables declared in the
+    /// header. The actual largest variable is given.
+    VariableCountMismatch(usize),
+}
+
+/// A mistake in a problem file, along with where it was found
+#[derive(Clone, Debug, PartialEq)]
+pub struct ParseError {
+    pub kind: ParseErrorKind,
+    /// The line the mistake is on, starting from 1
+    pub line: usize,
+    /// The column the offending token starts at, starting from 1
+    pub column: usize,
+    /// The offending token, which is empty if the line or file ended early
+    pub token: String,
+}
+
+impl ParseError {
+    pub(crate) fn at_end(kind: ParseErrorKind, line: usize) -> Self {
+        Self {
+            kind,
+            line,
+            column: 1,
+            token: String::new(),
+        }
+    }
+}
+
+impl Display for ParseError {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "line {}, column {}: ", self.line, self.column)?;
+        let found = match self.token.as_str() {
+            "" => "nothing".to_owned(),
+            token => format!("`{}`", token),
+        };
+        match self.kind {
+            ParseErrorKind::MissingHeader => {
+                write!(f, "expected a `p cnf` header, found {}", found)
+            }
+            ParseErrorKind::MalformedHeader => write!(
+                f,
+                "expected a header of the form `p cnf <variables> <clauses>`, found {}",
+                found
+            ),
+            ParseErrorKind::Wcnf(ref kind) => kind.describe(f, &found),
+            ParseErrorKind::Opb(ref kind) => kind.describe(f, &found),
+            ParseErrorKind::Icnf(ref kind) => kind.describe(f, &found),
+            ParseErrorKind::Aiger(ref kind) => kind.describe(f, &found),
+            ParseErrorKind::Qdimacs(ref kind) => kind.describe(f, &found),
+            ParseErrorKind::InvalidLiteral => {
+                write!(
+                    f,
+                    "expected a literal (a non-zero integer), found {}",
+                    found
+                )
+            }
+            ParseErrorKind::VariableOutOfRange(num_variables) => write!(
+                f,
+                "literal {} refers to a variable greater than the {} declared in the header",
+                found, num_variables
+            ),
+            ParseErrorKind::UnterminatedClause => {
+                write!(f, "the last clause isn't terminated by a 0")
+            }
+            ParseErrorKind::ClauseCountMismatch(actual) => write!(
+                f,
+                "the header declares {} clauses, but there are {}",
+                self.token, actual
+            ),
+            ParseErrorKind::VariableCountMismatch(actual) => write!(
+                f,
+                "the header declares {} variables, but the largest one is {}",
+                self.token, actual
+            ),
+        }
+    }
+}
+
+/// Opens `path`, including the path in the message of any error
+pub(crate) fn open(path: &Path) -> Result<File, Error> {
+    File::open(path).map_err(|e| {
+        Error::Io(std::io::Error::new(
+            e.kind(),
+            format!("{}: {}", path.display(), e),
+        ))
+    })
+}
+
+/// Hands a problem to `parse_line` a line at a time, decompressing it as in
+/// `read`
+pub(crate) fn read_lines(
+    reader: impl Read,
+    mut parse_line: impl FnMut(&str) -> Result<(), Error>,
+) -> Result<(), Error> {
+    let mut reader = decompress(reader)?;
+    let mut line = Vec::new();
+    while reader.read_until(b'\n', &mut line)? > 0 {
+        parse_line(&String::from_utf8_lossy(&line))?;
+        line.clear();
+    }
+    Ok(())
+}
+
+/// The kind, position and token of the error that parsing a problem failed
+/// with
+#[cfg(test)]
+pub(crate) fn parse_error<T: fmt::Debug>(
+    result: Result<T, Error>,
+) -> (ParseErrorKind, usize, usize, String) {
+    match result {
+        Err(Error::Parse(e)) => (e.kind, e.line, e.column, e.token),
+        result => panic!("Expected a parse error, found {:?}", result),
+    }
+}
+
+#[test]
+fn parse_cnf() -> Result<(), Error> {
+    let literal = |x: isize| Literal::from(x);
+
+    let cnf = parse(["c comment", "p cnf 3 2", "1 -2 3 0", "-3 0"])?;
+    assert_eq!(cnf.num_variables, 3);
+    assert_eq!(
+        cnf.clauses,
+        vec![vec![literal(1), literal(-2), literal(3)], vec![literal(-3)]]
+    );
+
+    let mut written = Vec::new();
+    cnf.write(&mut written)?;
+    assert_eq!(
+        String::from_utf8(written).unwrap(),
+        "p cnf 3 2\n1 -2 3 0\n-3 0\n"
+    );
+
+    assert!(matches!(
+        parse_file("inputs/missing.cnf"),
+        Err(Error::Io(_))
+    ));
+    Ok(())
+}
+
+#[test]
+fn tolerant() -> Result<(), Error> {
+    let literal = |x: isize| Literal::from(x);
+
+    let cnf = parse([
+        "c header comment",
+        "",
+        "p cnf 3 4",
+        "1 -2",
+        "c comment in the middle",
+        "  3 0 -3 0",
+        "0",
+        "2",
+        "%",
+        "0",
+        "",
+    ])?;
+    assert_eq!(
+        cnf,
+        Cnf {
+            num_variables: 3,
+            clauses: vec![
+                vec![literal(1), literal(-2), literal(3)],
+                vec![literal(-3)],
+                vec![],
+                vec![literal(2)],
+            ],
+            xors: Vec::new(),
+        }
+    );
+    Ok(())
+}
+
+#[test]
+fn load_into_solver() -> Result<(), Error> {
+    use crate::Solution;
+
+    let problem = "c comment\r\np cnf 2 3\r\n1\t2 0 -1 2\n0\n+1 -2 0";
+    let mut solver = Solver::new();
+    load(problem.as_bytes(), &mut solver, false)?;
+    assert_eq!(solver.num_variables(), 2);
+    match solver.solve()? {
+        Solution::Sat(model) => assert_eq!(
+            model,
+            vec![(0, crate::Sign::Positive), (1, crate::Sign::Positive)]
+        ),
+        _ => panic!("Expected Sat"),
+    }
+
+    let mut solver = Solver::new();
+    let error = load("p cnf 2 1\n1 -2x 0\n".as_bytes(), &mut solver, false);
+    assert!(matches!(
+        error,
+        Err(Error::Parse(ParseError {
+            kind: ParseErrorKind::InvalidLiteral,
+            line: 2,
+            column: 3,
+            ..
+        }))
+    ));
+    Ok(())
+}
+
+#[test]
+fn compressed() -> Result<(), Error> {
+    let problem = parse_file("inputs/aim-50.cnf")?;
+    let mut text = Vec::new();
+    problem.write(&mut text)?;
+
+    let mut gzip = flate2::write::GzEncoder::new(Vec::new(), Default::default());
+    gzip.write_all(&text)?;
+    let mut bzip2 = bzip2::write::BzEncoder::new(Vec::new(), bzip2::Compression::best());
+    bzip2.write_all(&text)?;
+    let mut xz = xz2::write::XzEncoder::new(Vec::new(), 6);
+    xz.write_all(&text)?;
+
+    for compressed in [gzip.finish()?, bzip2.finish()?, xz.finish()?].iter() {
+        assert_ne!(compressed, &text);
+        assert_eq!(read(compressed.as_slice(), true)?, problem);
+    }
+    assert_eq!(read(&b"p cnf 0 0"[..], false)?, Cnf::default());
+    Ok(())
+}
+
+#[test]
+fn strict() -> Result<(), Error> {
+    let error = |lines: &[&str]| parse_error(parse_strict(lines));
+
+    assert_eq!(
+        parse_strict(["p cnf 3 2", "1 -2", "3 0 -3 0"])?
+            .clauses
+            .len(),
+        2
+    );
+    assert_eq!(
+        error(&["p cnf 3 2", "1 -2 3 0", "-3"]),
+        (ParseErrorKind::UnterminatedClause, 4, 1, String::new())
+    );
+    assert_eq!(
+        error(&["p cnf 3 3", "1 -2 3 0", "-3 0"]),
+        (ParseErrorKind::ClauseCountMismatch(2), 1, 9, "3".to_owned())
+    );
+    assert_eq!(
+        error(&["p cnf  4 2", "1 -2 3 0", "-3 0"]),
+        (
+            ParseErrorKind::VariableCountMismatch(3),
+            1,
+            8,
+            "4".to_owned()
+        )
+    );
+    Ok(())
+}
+
+#[test]
+fn xor_lines() -> Result<(), Error> {
+    let literal = |x: isize| Literal::from(x);
+    let cnf = parse_strict(["p cnf 3 3", "x1 -2 0", "x 2", "3 0 1 3 0"])?;
+    assert_eq!(
+        cnf,
+        Cnf {
+            num_variables: 3,
+            clauses: vec![vec![literal(1), literal(3)]],
+            xors: vec![vec![literal(1), literal(-2)], vec![literal(2), literal(3)]],
+        }
+    );
+
+    let mut text = Vec::new();
+    cnf.write(&mut text)?;
+    assert_eq!(read(text.as_slice(), true)?, cnf);
+
+    // Only a whole clause can be an XOR constraint
+    assert!(matches!(
+        parse(["p cnf 2 1", "1 x2 0"]),
+        Err(Error::Parse(ParseError {
+            kind: ParseErrorKind::InvalidLiteral,
+            column: 3,
+            ..
+        }))
+    ));
+    Ok(())
+}
+
+#[test]
+fn parse_errors() {
+    let error = |lines: &[&str]| parse_error(parse(lines));
+
+    assert_eq!(
+        error(&[]),
+        (ParseErrorKind::MissingHeader, 1, 1, String::new())
+    );
+    assert_eq!(
+        error(&["c comment", "1 2 0"]),
+        (ParseErrorKind::MissingHeader, 2, 1, "1".to_owned())
+    );
+    assert_eq!(
+        error(&["p cnf 3"]),
+        (ParseErrorKind::MalformedHeader, 1, 8, String::new())
+    );
+    assert_eq!(
+        error(&["p  dnf 3 1"]),
+        (ParseErrorKind::MalformedHeader, 1, 4, "dnf".to_owned())
+    );
+    assert_eq!(
+        error(&["p cnf three 1"]),
+        (ParseErrorKind::MalformedHeader, 1, 7, "three".to_owned())
+    );
+    assert_eq!(
+        error(&["p cnf 1 1", "1 x 0"]),
+        (ParseErrorKind::InvalidLiteral, 2, 3, "x".to_owned())
+    );
+    assert_eq!(
+        error(&["p cnf 2 2", "1 2 0", "\t-1  -3 0"]),
+        (ParseErrorKind::VariableOutOfRange(2), 3, 6, "-3".to_owned())
+    );
+    // Variables too large for a literal, and a clause count too large to make
+    // room for
+    assert_eq!(
+        error(&["p cnf 18446744073709551615 1", "9223372036854775807 0"]),
+        (
+            ParseErrorKind::VariableOutOfRange((Variable::MAX >> 1) - 1),
+            2,
+            1,
+            "9223372036854775807".to_owned()
+        )
+    );
+    assert!(parse(["p cnf 1 18446744073709551615", "1 0"]).is_ok());
+    assert_eq!(
+        Error::from(ParseError {
+            kind: ParseErrorKind::InvalidLiteral,
+            line: 2,
+            column: 3,
+            token: "x".to_owned(),
+        })
+        .to_string(),
+        "Parse error: line 2, column 3: expected a literal (a non-zero integer), found `x`"
+    );
+}
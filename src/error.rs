@@ -0,0 +1,57 @@
+use crate::dimacs::ParseError;
+use std::fmt::{self, Display, Formatter};
+
+/// The ways in which reading a problem, solving it or checking a proof can
+/// fail
+#[derive(Debug)]
+pub enum Error {
+    /// Reading or writing a file or stream failed
+    Io(std::io::Error),
+    /// A problem file isn't valid DIMACS
+    Parse(ParseError),
+    /// An argument doesn't make sense, such as an unknown proof format or
+    /// popping a clause group that was never pushed
+    InvalidInput(String),
+    /// A proof doesn't show that its problem is unsatisfiable
+    InvalidProof(String),
+    /// The search was stopped by its budget or an interrupt before it could
+    /// finish
+    ResourceLimit,
+    /// One of the solver's invariants doesn't hold, which is a bug in the
+    /// solver
+    Internal(String),
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            Error::Io(e) => write!(f, "{}", e),
+            Error::Parse(e) => write!(f, "Parse error: {}", e),
+            Error::InvalidInput(message) => write!(f, "{}", message),
+            Error::InvalidProof(message) => write!(f, "Invalid proof: {}", message),
+            Error::ResourceLimit => write!(f, "Search stopped before it finished"),
+            Error::Internal(message) => write!(f, "Internal error: {}", message),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<ParseError> for Error {
+    fn from(e: ParseError) -> Self {
+        Error::Parse(e)
+    }
+}
+
+impl From<std::io::Error> for Error {
+    fn from(e: std::io::Error) -> Self {
+        Error::Io(e)
+    }
+}
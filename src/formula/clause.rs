@@ -1,9 +1,22 @@
-use crate::{Assignments, ClauseIdx, Evaluate, Literal, Watched};
+use crate::{Assignments, ClauseIdx, Error, Evaluate, Literal, Watched};
 
 #[derive(Debug)]
 pub enum Clause {
-    Binary { a: Literal, b: Literal },
-    Many { literals: Vec<Literal> },
+    Binary {
+        a: Literal,
+        b: Literal,
+    },
+    Many {
+        literals: Vec<Literal>,
+    },
+    /// A pseudo-Boolean constraint: the coefficients of the true literals add
+    /// up to at least the degree. Coefficients are sorted in decreasing order
+    /// and are no greater than the degree, and every literal is watched.
+    Pb {
+        literals: Vec<Literal>,
+        coefficients: Vec<u64>,
+        degree: u64,
+    },
 }
 
 #[must_use]
@@ -17,9 +30,11 @@ pub enum Status {
 impl Clause {
     pub fn new(
         mut literals: impl Iterator<Item = Literal> + ExactSizeIterator,
-    ) -> Result<Self, String> {
+    ) -> Result<Self, Error> {
         match literals.len() {
-            0 | 1 => Err("Formula should only contain clauses with len > 1".to_string()),
+            0 | 1 => Err(Error::Internal(
+                "Formula should only contain clauses with len > 1".to_string(),
+            )),
             2 => Ok(Self::Binary {
                 a: literals.next().unwrap(),
                 b: literals.next().unwrap(),
@@ -30,6 +45,76 @@ impl Clause {
         }
     }
 
+    /// A constraint that the coefficients of the true literals add up to at
+    /// least `degree`, given as pairs of a coefficient and a literal. Each
+    /// literal should appear once, with a coefficient no greater than
+    /// `degree`, and at least two literals are needed for the constraint not
+    /// to be a unit.
+    pub fn pb(mut terms: Vec<(u64, Literal)>, degree: u64) -> Result<Self, Error> {
+        if terms.len() < 2 {
+            return Err(Error::Internal(
+                "Formula should only contain constraints with len > 1".to_string(),
+            ));
+        }
+        terms.sort_by_key(|(coefficient, _)| std::cmp::Reverse(*coefficient));
+        let (coefficients, literals) = terms.into_iter().unzip();
+        Ok(Self::Pb {
+            literals,
+            coefficients,
+            degree,
+        })
+    }
+
+    /// The literals in the clause, in no particular order
+    pub fn literals<'a>(&'a self) -> impl Iterator<Item = Literal> + 'a {
+        let (pair, rest) = match self {
+            Self::Binary { a, b } => (Some([*a, *b]), &[][..]),
+            Self::Many { literals } | Self::Pb { literals, .. } => (None, &literals[..]),
+        };
+        pair.into_iter().flatten().chain(rest.iter().copied())
+    }
+
+    /// The literals currently being watched: two for a clause, and all of
+    /// them for a pseudo-Boolean constraint
+    pub fn watched<'a>(&'a self) -> impl Iterator<Item = Literal> + 'a {
+        let (pair, rest) = match self {
+            Self::Binary { a, b } => (Some([*a, *b]), &[][..]),
+            Self::Many { literals } => (None, &literals[..2]),
+            Self::Pb { literals, .. } => (None, &literals[..]),
+        };
+        pair.into_iter().flatten().chain(rest.iter().copied())
+    }
+
+    /// The false literals that made the clause imply `implied`, or that make
+    /// it conflicting if there is no implied literal. For a pseudo-Boolean
+    /// constraint these are the literals falsified before `implied` was
+    /// assigned, which is worked out afresh since propagation doesn't record
+    /// them.
+    pub fn reason<'a>(
+        &'a self,
+        implied: Option<Literal>,
+        assignments: &'a Assignments,
+    ) -> impl Iterator<Item = Literal> + 'a {
+        let before = match self {
+            Self::Pb { .. } => implied
+                .and_then(|literal| assignments.get(literal.var()))
+                .map_or(usize::MAX, |assignment| assignment.order()),
+            _ => usize::MAX,
+        };
+        self.literals().filter(move |literal| {
+            Some(*literal) != implied
+                && match self {
+                    Self::Pb { .. } => {
+                        literal.evaluate(assignments) == Some(false)
+                            && assignments
+                                .get(literal.var())
+                                .is_some_and(|assignment| assignment.order() < before)
+                    }
+                    _ => true,
+                }
+        })
+    }
+
     /// Restores the 2-Watched Literal invariant and produces a new implied literal if one exists
     pub fn update(
         &mut self,
@@ -90,12 +175,40 @@ impl Clause {
                     },
                 }
             }
+            Self::Pb {
+                literals,
+                coefficients,
+                degree,
+            } => {
+                // The slack is how far the coefficients of the literals that
+                // aren't false exceed the degree
+                let possible = literals
+                    .iter()
+                    .zip(coefficients.iter())
+                    .filter(|(literal, _)| literal.evaluate(assignments) != Some(false))
+                    .map(|(_, coefficient)| coefficient)
+                    .sum::<u64>();
+                let slack = match possible.checked_sub(*degree) {
+                    Some(slack) => slack,
+                    None => return Status::Conflict,
+                };
+
+                // Any unassigned literal with a coefficient greater than the
+                // slack must be true. Only one is reported at a time, so the
+                // constraint has to be updated again until none are left.
+                literals
+                    .iter()
+                    .zip(coefficients.iter())
+                    .take_while(|(_, coefficient)| **coefficient > slack)
+                    .find(|(literal, _)| literal.evaluate(assignments).is_none())
+                    .map_or(Status::Ok, |(literal, _)| Status::Implied(*literal))
+            }
         }
     }
 }
 
 #[test]
-fn new_clause() -> Result<(), String> {
+fn new_clause() -> Result<(), Error> {
     assert!(Clause::new(std::iter::empty()).is_err());
     assert!(Clause::new(std::iter::once(Literal::new(5, false))).is_err());
 
@@ -121,7 +234,7 @@ fn new_clause() -> Result<(), String> {
 }
 
 #[test]
-fn update_binary() -> Result<(), String> {
+fn update_binary() -> Result<(), Error> {
     use crate::sign::Sign::{Negative, Positive};
 
     let (l0, l1) = (Literal::new(0, true), Literal::new(1, true));
@@ -192,7 +305,7 @@ fn update_binary() -> Result<(), String> {
 }
 
 #[test]
-fn update_ternary() -> Result<(), String> {
+fn update_ternary() -> Result<(), Error> {
     use crate::sign::Sign::{Negative, Positive};
 
     let (l0, l1, l2) = (
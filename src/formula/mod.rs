@@ -1,4 +1,4 @@
-use crate::{Assignments, ClauseIdx, Counters, Literal, Variable, Watched};
+use crate::{Assignments, ClauseId, ClauseIdx, Counters, Error, Literal, Variable, Watched};
 use std::ops::{Index, IndexMut};
 
 pub mod clause;
@@ -7,39 +7,120 @@ pub use clause::{Clause, Status};
 
 /// A formula that contains no empty or unit clauses
 pub struct Formula {
-    clauses: Vec<Clause>,
+    clauses: Vec<Option<Clause>>,
+    ids: Vec<ClauseId>,
+    free: Vec<ClauseIdx>,
 }
 
 impl Formula {
     pub fn new(num_clauses: usize) -> Self {
         Self {
             clauses: Vec::with_capacity(num_clauses),
+            ids: Vec::with_capacity(num_clauses),
+            free: Vec::new(),
         }
     }
 
     pub fn add_clause(
         &mut self,
+        id: ClauseId,
         literals: impl Iterator<Item = Literal> + ExactSizeIterator,
         watched: &mut Watched,
         counters: &mut Counters<Variable>,
         assignments: &Assignments,
-    ) -> Result<(ClauseIdx, Status), String> {
+    ) -> Result<(ClauseIdx, Status), Error> {
         let literals = literals.inspect(|literal| counters.bump(literal.var()));
         let clause = Clause::new(literals)?;
-        let idx = self.clauses.len();
-        match &clause {
-            Clause::Binary { a, b } => {
-                watched[*a].push(idx);
-                watched[*b].push(idx);
+        Ok(self.insert(id, clause, watched, assignments))
+    }
+
+    /// Adds a pseudo-Boolean constraint, given as in `Clause::pb`
+    pub fn add_pb(
+        &mut self,
+        id: ClauseId,
+        terms: Vec<(u64, Literal)>,
+        degree: u64,
+        watched: &mut Watched,
+        counters: &mut Counters<Variable>,
+        assignments: &Assignments,
+    ) -> Result<(ClauseIdx, Status), Error> {
+        for (_, literal) in terms.iter() {
+            counters.bump(literal.var());
+        }
+        let clause = Clause::pb(terms, degree)?;
+        Ok(self.insert(id, clause, watched, assignments))
+    }
+
+    /// Adds a clause that only explains an assignment or conflict found some
+    /// other way. It isn't watched, so it never propagates, and should be
+    /// removed with `remove` once it's no longer needed.
+    pub fn add_reason(&mut self, id: ClauseId, literals: Vec<Literal>) -> ClauseIdx {
+        let clause = Clause::new(literals.into_iter()).expect("reasons have at least two literals");
+        let idx = self.slot(id);
+        self.clauses[idx] = Some(clause);
+        idx
+    }
+
+    /// Removes a clause added by `add_reason`. Its index may be reused by
+    /// clauses added later.
+    pub fn remove(&mut self, idx: ClauseIdx) {
+        self.clauses[idx] = None;
+        self.free.push(idx);
+    }
+
+    fn insert(
+        &mut self,
+        id: ClauseId,
+        clause: Clause,
+        watched: &mut Watched,
+        assignments: &Assignments,
+    ) -> (ClauseIdx, Status) {
+        let idx = self.slot(id);
+        for literal in clause.watched() {
+            watched[literal].push(idx);
+        }
+        self.clauses[idx] = Some(clause);
+        (idx, self[idx].update(watched, assignments, idx))
+    }
+
+    /// Finds an index for a new clause with the given ID
+    fn slot(&mut self, id: ClauseId) -> ClauseIdx {
+        let idx = self.free.pop().unwrap_or_else(|| {
+            self.clauses.push(None);
+            self.ids.push(id);
+            self.clauses.len() - 1
+        });
+        self.ids[idx] = id;
+        idx
+    }
+
+    /// The proof ID of the clause at `idx`
+    pub fn id(&self, idx: ClauseIdx) -> ClauseId {
+        self.ids[idx]
+    }
+
+    /// Removes every clause containing `literal`, returning them along with
+    /// their indices and IDs. Removed indices may be reused by clauses added
+    /// later.
+    pub fn remove_containing(
+        &mut self,
+        literal: Literal,
+        watched: &mut Watched,
+    ) -> Vec<(ClauseIdx, ClauseId, Clause)> {
+        let mut removed = Vec::new();
+        for (idx, slot) in self.clauses.iter_mut().enumerate() {
+            if !matches!(slot, Some(clause) if clause.literals().any(|other| other == literal)) {
+                continue;
             }
-            Clause::Many { literals } => {
-                watched[literals[0]].push(idx);
-                watched[literals[1]].push(idx);
+            if let Some(clause) = slot.take() {
+                for literal in clause.watched() {
+                    watched[literal].retain(|other| *other != idx);
+                }
+                self.free.push(idx);
+                removed.push((idx, self.ids[idx], clause));
             }
         }
-        let idx = self.clauses.len();
-        self.clauses.push(clause);
-        Ok((idx, self.clauses[idx].update(watched, assignments, idx)))
+        removed
     }
 }
 
@@ -47,18 +128,18 @@ impl Index<ClauseIdx> for Formula {
     type Output = Clause;
 
     fn index(&self, idx: ClauseIdx) -> &Self::Output {
-        &self.clauses[idx]
+        self.clauses[idx].as_ref().expect("clause was removed")
     }
 }
 
 impl IndexMut<ClauseIdx> for Formula {
     fn index_mut(&mut self, idx: ClauseIdx) -> &mut Self::Output {
-        &mut self.clauses[idx]
+        self.clauses[idx].as_mut().expect("clause was removed")
     }
 }
 
 #[test]
-fn add_clause() -> Result<(), String> {
+fn add_clause() -> Result<(), Error> {
     use crate::sign::Sign::Positive;
 
     let mut formula = Formula::new(2);
@@ -69,7 +150,7 @@ fn add_clause() -> Result<(), String> {
     let (l0, l1) = (Literal::new(0, Positive), Literal::new(1, Positive));
 
     assert_eq!(
-        formula.add_clause([l0, l1].iter().copied(), watched, counters, assignments)?,
+        formula.add_clause(1, [l0, l1].iter().copied(), watched, counters, assignments)?,
         (0, Status::Ok)
     );
     assert_eq!(counters[0], 1.0);
@@ -82,7 +163,7 @@ fn add_clause() -> Result<(), String> {
 
     assignments.set_unchecked(0, Positive);
     assert_eq!(
-        formula.add_clause([!l0, l1].iter().copied(), watched, counters, assignments)?,
+        formula.add_clause(2, [!l0, l1].iter().copied(), watched, counters, assignments)?,
         (1, Status::Implied(l1))
     );
     assert_eq!(counters[0], 2.0);
@@ -99,3 +180,50 @@ fn add_clause() -> Result<(), String> {
 
     Ok(())
 }
+
+#[test]
+fn remove_containing() -> Result<(), Error> {
+    use crate::sign::Sign::Positive;
+
+    let mut formula = Formula::new(3);
+    let watched = &mut Watched::new(3);
+    let counters = &mut Counters::new(3);
+    let assignments = &Assignments::new(3);
+
+    let (l0, l1, l2) = (
+        Literal::new(0, Positive),
+        Literal::new(1, Positive),
+        Literal::new(2, Positive),
+    );
+
+    let _ = formula.add_clause(1, [l0, l1].iter().copied(), watched, counters, assignments)?;
+    let _ = formula.add_clause(
+        2,
+        [l1, l2, !l0].iter().copied(),
+        watched,
+        counters,
+        assignments,
+    )?;
+    let _ = formula.add_clause(3, [l2, !l0].iter().copied(), watched, counters, assignments)?;
+
+    let removed = formula.remove_containing(!l0, watched);
+    assert_eq!(
+        removed
+            .iter()
+            .map(|(idx, id, _)| (*idx, *id))
+            .collect::<Vec<_>>(),
+        vec![(1, 2), (2, 3)]
+    );
+    assert_eq!(watched[l0], vec![0]);
+    assert_eq!(watched[l1], vec![0]);
+    assert!(watched[l2].is_empty());
+    assert!(watched[!l0].is_empty());
+
+    // Freed indices are reused
+    let (idx, _) =
+        formula.add_clause(4, [l1, l2].iter().copied(), watched, counters, assignments)?;
+    assert!(idx == 1 || idx == 2);
+    assert_eq!(formula.id(idx), 4);
+    assert_eq!(formula.clauses.len(), 3);
+    Ok(())
+}
@@ -0,0 +1,313 @@
+use crate::{Assignments, Literal, Variable};
+use fixedbitset::FixedBitSet;
+
+/// Something the XOR constraints found, given as the literals of a clause
+/// that explains it
+#[derive(Debug, PartialEq)]
+pub enum Propagation {
+    /// A row has been reduced to a single column, so this literal holds
+    /// whatever else is assigned
+    Unit(Literal),
+    /// The first literal is implied by the others, which are all false
+    Implied(Vec<Literal>),
+    /// Every literal is false
+    Conflict(Vec<Literal>),
+}
+
+/// XOR constraints, kept as a matrix in reduced row echelon form over the
+/// variables they mention. Each row has a basic column, which appears in no
+/// other row, and watches it along with one other unassigned column, so a
+/// row only needs looking at once one of those two is assigned. When the
+/// basic column of a row is assigned, an unassigned column takes its place
+/// and is eliminated from every other row, which keeps the matrix reduced
+/// under the current assignment.
+#[derive(Default)]
+pub struct Gauss {
+    /// The constraints as they were added, each given by its variables and
+    /// the parity of the true ones
+    constraints: Vec<(Vec<Variable>, bool)>,
+    /// Whether constraints have been added since the matrix was built
+    dirty: bool,
+    /// The column of each variable that appears in a constraint
+    columns: Vec<Option<usize>>,
+    /// The variable of each column
+    variables: Vec<Variable>,
+    rows: Vec<Row>,
+    /// The rows watching each column. Rows that have since moved on to
+    /// another column are only removed once the column is assigned.
+    watchers: Vec<Vec<usize>>,
+    pending: Vec<usize>,
+    queued: FixedBitSet,
+    /// Whether the search has backtracked since rows were last updated
+    stale: bool,
+}
+
+struct Row {
+    columns: FixedBitSet,
+    /// The parity of the values of the columns
+    parity: bool,
+    basic: usize,
+    watch: usize,
+}
+
+impl Gauss {
+    /// Adds a constraint that an odd number of `variables` are true if
+    /// `parity` is set, or an even number otherwise. A variable given twice
+    /// cancels out. The constraint takes effect once the matrix is rebuilt.
+    pub fn add(&mut self, variables: Vec<Variable>, parity: bool) {
+        self.constraints.push((variables, parity));
+        self.dirty = true;
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.constraints.is_empty()
+    }
+
+    pub fn needs_rebuild(&self) -> bool {
+        self.dirty
+    }
+
+    /// The variables of a constraint that `assignments` falsifies, if any
+    pub fn falsified(&self, assignments: &Assignments) -> Option<&[Variable]> {
+        self.constraints
+            .iter()
+            .find(|(variables, parity)| {
+                let values = variables
+                    .iter()
+                    .filter(|var| matches!(assignments.get(**var), Some(a) if a.sign().into()));
+                (values.count() % 2 == 1) != *parity
+            })
+            .map(|(variables, _)| &variables[..])
+    }
+
+    /// Builds the matrix from scratch by Gauss-Jordan elimination, choosing
+    /// unassigned columns as basic ones where possible, and queues every row
+    /// to be updated. Returns false if the constraints contradict each other.
+    pub fn rebuild(&mut self, assignments: &Assignments) -> bool {
+        self.dirty = false;
+        self.columns.clear();
+        self.variables.clear();
+        for var in self.constraints.iter().flat_map(|(variables, _)| variables) {
+            if self.columns.len() <= *var {
+                self.columns.resize(var + 1, None);
+            }
+            if self.columns[*var].is_none() {
+                self.columns[*var] = Some(self.variables.len());
+                self.variables.push(*var);
+            }
+        }
+
+        let width = self.variables.len();
+        let mut rows = self
+            .constraints
+            .iter()
+            .map(|(variables, parity)| {
+                let mut columns = FixedBitSet::with_capacity(width);
+                for var in variables {
+                    columns.toggle(self.columns[*var].unwrap());
+                }
+                (columns, *parity)
+            })
+            .collect::<Vec<_>>();
+
+        let mut order = (0..width).collect::<Vec<_>>();
+        order.sort_by_key(|column| assignments[self.variables[*column]].is_some());
+        let mut basics = Vec::new();
+        for column in order {
+            let rank = basics.len();
+            let pivot = match (rank..rows.len()).find(|row| rows[*row].0.contains(column)) {
+                Some(pivot) => pivot,
+                None => continue,
+            };
+            rows.swap(rank, pivot);
+            let (columns, parity) = rows[rank].clone();
+            for (idx, row) in rows.iter_mut().enumerate() {
+                if idx != rank && row.0.contains(column) {
+                    row.0.symmetric_difference_with(&columns);
+                    row.1 ^= parity;
+                }
+            }
+            basics.push(column);
+        }
+
+        // The rows left over have no columns at all
+        if rows[basics.len()..].iter().any(|(_, parity)| *parity) {
+            return false;
+        }
+        rows.truncate(basics.len());
+        self.rows = rows
+            .into_iter()
+            .zip(basics)
+            .map(|((columns, parity), basic)| {
+                let watch = columns.ones().find(|c| *c != basic).unwrap_or(basic);
+                Row {
+                    columns,
+                    parity,
+                    basic,
+                    watch,
+                }
+            })
+            .collect();
+
+        self.watchers = vec![Vec::new(); width];
+        for (idx, row) in self.rows.iter().enumerate() {
+            self.watchers[row.basic].push(idx);
+            if row.watch != row.basic {
+                self.watchers[row.watch].push(idx);
+            }
+        }
+        self.pending = (0..self.rows.len()).collect();
+        self.queued = FixedBitSet::with_capacity(self.rows.len());
+        self.queued.insert_range(..);
+        self.stale = false;
+        true
+    }
+
+    /// Queues the rows watching a variable that has just been assigned
+    pub fn assigned(&mut self, var: Variable) {
+        let column = match self.columns.get(var) {
+            Some(Some(column)) => *column,
+            _ => return,
+        };
+        let rows = &self.rows;
+        let watchers = &mut self.watchers[column];
+        watchers.retain(|row| rows[*row].basic == column || rows[*row].watch == column);
+        watchers.sort_unstable();
+        watchers.dedup();
+        for row in watchers.iter() {
+            if !self.queued.put(*row) {
+                self.pending.push(*row);
+            }
+        }
+    }
+
+    /// Notes that assignments have been undone, after which a row may need
+    /// updating even though neither of its watched columns is assigned
+    pub fn backtracked(&mut self) {
+        self.stale = true;
+    }
+
+    /// Updates queued rows until one of them implies a literal or conflicts
+    pub fn next(&mut self, assignments: &Assignments) -> Option<Propagation> {
+        if self.stale {
+            self.refresh(assignments);
+        }
+        while let Some(row) = self.pending.pop() {
+            self.queued.set(row, false);
+            if let Some(propagation) = self.update(row, assignments) {
+                return Some(propagation);
+            }
+        }
+        None
+    }
+
+    /// Queues every row that isn't known to have two unassigned columns
+    /// watched. Rows stop watching unassigned columns once they imply or
+    /// conflict, and may have lost a watched column to an elimination that
+    /// was never followed up on.
+    fn refresh(&mut self, assignments: &Assignments) {
+        self.stale = false;
+        self.pending.clear();
+        self.queued.clear();
+        let variables = &self.variables;
+        let assigned = |column: usize| assignments[variables[column]].is_some();
+        for (idx, row) in self.rows.iter().enumerate() {
+            if row.watch == row.basic
+                || !row.columns.contains(row.watch)
+                || assigned(row.basic)
+                || assigned(row.watch)
+            {
+                self.queued.insert(idx);
+                self.pending.push(idx);
+            }
+        }
+    }
+
+    fn update(&mut self, idx: usize, assignments: &Assignments) -> Option<Propagation> {
+        let variables = &self.variables;
+        let value = |column: usize| -> Option<bool> {
+            assignments[variables[column]]
+                .as_ref()
+                .map(|assignment| assignment.sign().into())
+        };
+
+        let row = &mut self.rows[idx];
+        if row.columns.count_ones(..) == 1 {
+            row.watch = row.basic;
+            let literal = Literal::new(variables[row.basic], row.parity);
+            return match value(row.basic) == Some(row.parity) {
+                true => None,
+                false => Some(Propagation::Unit(literal)),
+            };
+        }
+
+        if value(row.basic).is_some() {
+            let column = row.columns.ones().find(|c| value(*c).is_none());
+            if let Some(column) = column {
+                let queue = (&mut self.pending, &mut self.queued);
+                pivot(&mut self.rows, &mut self.watchers, queue, idx, column);
+            }
+        }
+        let row = &mut self.rows[idx];
+        let basic_value = value(row.basic);
+        if basic_value.is_none() {
+            let basic = row.basic;
+            if let Some(watch) = row
+                .columns
+                .ones()
+                .find(|c| *c != basic && value(*c).is_none())
+            {
+                if watch != row.watch {
+                    row.watch = watch;
+                    self.watchers[watch].push(idx);
+                }
+                return None;
+            }
+        }
+
+        // At most the basic column is unassigned, and its value has to make
+        // up the parity of the rest
+        let mut parity = row.parity;
+        let mut clause = Vec::new();
+        for column in row.columns.ones() {
+            if let Some(value) = value(column) {
+                parity ^= value;
+                clause.push(Literal::new(variables[column], !value));
+            }
+        }
+        match basic_value {
+            None => {
+                clause.insert(0, Literal::new(variables[row.basic], parity));
+                Some(Propagation::Implied(clause))
+            }
+            Some(_) if parity => Some(Propagation::Conflict(clause)),
+            Some(_) => None,
+        }
+    }
+}
+
+/// Makes `column` the basic column of row `idx`, eliminating it from the
+/// other rows and queueing them to be updated
+fn pivot(
+    rows: &mut [Row],
+    watchers: &mut [Vec<usize>],
+    (pending, queued): (&mut Vec<usize>, &mut FixedBitSet),
+    idx: usize,
+    column: usize,
+) {
+    let columns = std::mem::take(&mut rows[idx].columns);
+    let parity = rows[idx].parity;
+    for (other, row) in rows.iter_mut().enumerate() {
+        if other != idx && row.columns.contains(column) {
+            row.columns.symmetric_difference_with(&columns);
+            row.parity ^= parity;
+            if !queued.put(other) {
+                pending.push(other);
+            }
+        }
+    }
+    let row = &mut rows[idx];
+    row.columns = columns;
+    row.basic = column;
+    watchers[column].push(idx);
+}
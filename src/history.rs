@@ -48,6 +48,11 @@ impl History {
         }
     }
 
+    /// Literals assigned above decision level 0, in the order they were assigned
+    pub fn assigned(&self) -> &[Literal] {
+        &self.assignments
+    }
+
     pub fn num_assigned(&self) -> usize {
         self.assignments.len() + self.invariants.len()
     }
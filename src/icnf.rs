@@ -0,0 +1,370 @@
+use crate::{
+    dimacs::{open, read_lines, tokens, ParseError, ParseErrorKind, MAX_VARIABLE},
+    Error, Literal, Sign, Solution, Solver, Variable,
+};
+use std::{
+    fmt::{self, Formatter},
+    io::Read,
+    path::Path,
+};
+
+/// An incremental problem, as read from an iCNF file
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Icnf {
+    pub steps: Vec<Step>,
+}
+
+/// A clause or query of an incremental problem
+#[derive(Clone, Debug, PartialEq)]
+pub enum Step {
+    /// A clause that every later query has to satisfy
+    Clause(Vec<Literal>),
+    /// A query of whether the clauses so far can be satisfied with these
+    /// literals assumed true
+    Assume(Vec<Literal>),
+}
+
+/// The ways in which an iCNF file can fail to be valid, beyond those it shares
+/// with DIMACS
+#[derive(Clone, Debug, PartialEq)]
+pub enum ErrorKind {
+    /// The first line that isn't a comment doesn't start with `p`
+    MissingHeader,
+    /// The header isn't exactly `p inccnf`
+    MalformedHeader,
+}
+
+impl ErrorKind {
+    pub(crate) fn describe(&self, f: &mut Formatter, found: &str) -> fmt::Result {
+        match self {
+            ErrorKind::MissingHeader => {
+                write!(f, "expected a `p inccnf` header, found {}", found)
+            }
+            ErrorKind::MalformedHeader => {
+                write!(
+                    f,
+                    "expected a header of exactly `p inccnf`, found {}",
+                    found
+                )
+            }
+        }
+    }
+}
+
+pub fn parse_file(path: impl AsRef<Path>) -> Result<Icnf, Error> {
+    read(open(path.as_ref())?)
+}
+
+/// Reads a problem in iCNF format, decompressing it as in `dimacs::read`
+pub fn read(reader: impl Read) -> Result<Icnf, Error> {
+    let mut parser = Parser::default();
+    read_lines(reader, |line| parser.parse_line(line))?;
+    parser.finish()
+}
+
+/// Parses a problem in iCNF format: a `p inccnf` header followed by clauses,
+/// laid out as in `dimacs::parse`, and queries, each of which starts with `a`
+/// and lists the literals to assume until a terminating 0
+pub fn parse(lines: impl IntoIterator<Item = impl AsRef<str>>) -> Result<Icnf, Error> {
+    let mut parser = Parser::default();
+    for line in lines {
+        parser.parse_line(line.as_ref())?;
+    }
+    parser.finish()
+}
+
+/// Answers each query of an incremental problem in turn, using one solver
+/// throughout so that what's learned for one query carries over to the
+/// next. Each solution is passed to `answered`, along with the assumptions
+/// responsible if it's `Unsat`.
+pub fn solve(
+    icnf: &Icnf,
+    mut answered: impl FnMut(Solution<Vec<(Variable, Sign)>>, &[Literal]),
+) -> Result<(), Error> {
+    let mut solver = Solver::new();
+    for step in icnf.steps.iter() {
+        match step {
+            Step::Clause(clause) => solver.add_clause(clause)?,
+            Step::Assume(assumptions) => {
+                let solution = solver.solve_with_assumptions(assumptions)?;
+                answered(solution, solver.failed_assumptions());
+            }
+        }
+    }
+    Ok(())
+}
+
+#[derive(Default)]
+struct Parser {
+    icnf: Icnf,
+    /// The number of the last line parsed
+    line: usize,
+    header: bool,
+    /// Whether the step being parsed is a query, and its literals so far
+    step: Option<(bool, Vec<Literal>)>,
+}
+
+impl Parser {
+    fn parse_line(&mut self, line: &str) -> Result<(), Error> {
+        self.line += 1;
+        let tokens = tokens(line);
+        match tokens.first() {
+            None => return Ok(()),
+            Some((_, token)) if token.starts_with('c') => return Ok(()),
+            _ if !self.header => {
+                self.parse_header(line, &tokens)?;
+                self.header = true;
+                return Ok(());
+            }
+            _ => {}
+        }
+
+        let number = self.line;
+        for (column, token) in tokens {
+            let (query, literals) = self.step.get_or_insert_with(|| (false, Vec::new()));
+            if token == "a" && !*query && literals.is_empty() {
+                *query = true;
+                continue;
+            }
+            // There's no header to bound the variables, so they're bounded
+            // here before the solver makes room for them
+            let literal = token
+                .parse::<isize>()
+                .ok()
+                .filter(|literal| {
+                    literal
+                        .checked_abs()
+                        .is_some_and(|var| var as usize <= MAX_VARIABLE)
+                })
+                .ok_or_else(|| ParseError {
+                    kind: ParseErrorKind::InvalidLiteral,
+                    line: number,
+                    column,
+                    token: token.to_owned(),
+                })?;
+            if literal != 0 {
+                literals.push(Literal::from(literal));
+                continue;
+            }
+            self.end_step();
+        }
+        Ok(())
+    }
+
+    fn parse_header(&self, line: &str, tokens: &[(usize, &str)]) -> Result<(), ParseError> {
+        let error = |kind, idx: usize| match tokens.get(idx) {
+            Some((column, token)) => ParseError {
+                kind,
+                line: self.line,
+                column: *column,
+                token: (*token).to_owned(),
+            },
+            None => ParseError {
+                column: line.chars().count() + 1,
+                ..ParseError::at_end(kind, self.line)
+            },
+        };
+
+        if tokens[0].1 != "p" {
+            return Err(error(ParseErrorKind::Icnf(ErrorKind::MissingHeader), 0));
+        }
+        if tokens.get(1).map(|(_, token)| *token) != Some("inccnf") {
+            return Err(error(ParseErrorKind::Icnf(ErrorKind::MalformedHeader), 1));
+        }
+        if tokens.len() > 2 {
+            return Err(error(ParseErrorKind::Icnf(ErrorKind::MalformedHeader), 2));
+        }
+        Ok(())
+    }
+
+    fn end_step(&mut self) {
+        if let Some((query, literals)) = self.step.take() {
+            self.icnf.steps.push(match query {
+                true => Step::Assume(literals),
+                false => Step::Clause(literals),
+            });
+        }
+    }
+
+    /// Returns the problem, including a last step without a terminating 0
+    fn finish(mut self) -> Result<Icnf, Error> {
+        if !self.header {
+            return Err(ParseError::at_end(
+                ParseErrorKind::Icnf(ErrorKind::MissingHeader),
+                self.line + 1,
+            )
+            .into());
+        }
+        self.end_step();
+        Ok(self.icnf)
+    }
+}
+
+#[test]
+fn parse_icnf() -> Result<(), Error> {
+    let literal = |x: isize| Literal::from(x);
+    let icnf = parse([
+        "c incremental",
+        "p inccnf",
+        "1 -2 0 2",
+        "3 0",
+        "a -1 0 a 0",
+        "-3 0",
+        "a 2",
+    ])?;
+    assert_eq!(
+        icnf.steps,
+        vec![
+            Step::Clause(vec![literal(1), literal(-2)]),
+            Step::Clause(vec![literal(2), literal(3)]),
+            Step::Assume(vec![literal(-1)]),
+            Step::Assume(vec![]),
+            Step::Clause(vec![literal(-3)]),
+            Step::Assume(vec![literal(2)]),
+        ]
+    );
+    Ok(())
+}
+
+#[test]
+fn icnf_errors() {
+    use crate::dimacs::parse_error;
+    let error = |lines: &[&str]| parse_error(parse(lines));
+    assert_eq!(
+        error(&["1 2 0"]),
+        (
+            ParseErrorKind::Icnf(ErrorKind::MissingHeader),
+            1,
+            1,
+            "1".to_owned()
+        )
+    );
+    assert_eq!(
+        error(&["c empty"]),
+        (
+            ParseErrorKind::Icnf(ErrorKind::MissingHeader),
+            2,
+            1,
+            String::new()
+        )
+    );
+    assert_eq!(
+        error(&["p cnf 2 1"]),
+        (
+            ParseErrorKind::Icnf(ErrorKind::MalformedHeader),
+            1,
+            3,
+            "cnf".to_owned()
+        )
+    );
+    assert_eq!(
+        error(&["p inccnf", "1 a 2 0"]),
+        (ParseErrorKind::InvalidLiteral, 2, 3, "a".to_owned())
+    );
+    assert_eq!(
+        error(&["p inccnf", "1 -9223372036854775808 0"]),
+        (
+            ParseErrorKind::InvalidLiteral,
+            2,
+            3,
+            "-9223372036854775808".to_owned()
+        )
+    );
+    assert_eq!(
+        error(&["p inccnf", "a 4000000000000 0"]),
+        (
+            ParseErrorKind::InvalidLiteral,
+            2,
+            3,
+            "4000000000000".to_owned()
+        )
+    );
+}
+
+#[cfg(test)]
+impl quickcheck::Arbitrary for Icnf {
+    /// A problem small enough to answer by brute force, with a query after
+    /// every three clauses
+    fn arbitrary<G: quickcheck::Gen>(g: &mut G) -> Self {
+        use rand::Rng;
+
+        let num_variables = g.gen_range(1, 8);
+        let mut random = |len: usize| {
+            (0..len)
+                .map(|_| Literal::new(g.gen_range(0, num_variables), g.gen::<bool>()))
+                .collect::<Vec<_>>()
+        };
+        Icnf {
+            steps: (0..20)
+                .map(|step| match step % 4 {
+                    3 => Step::Assume(random(step % 3)),
+                    _ => Step::Clause(random(3)),
+                })
+                .collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+#[quickcheck_macros::quickcheck]
+fn matches_brute_force(icnf: Icnf) -> Result<(), Error> {
+    let steps = &icnf.steps;
+    let num_variables = steps
+        .iter()
+        .flat_map(|step| match step {
+            Step::Clause(literals) | Step::Assume(literals) => literals.iter(),
+        })
+        .map(|literal| literal.var() + 1)
+        .max()
+        .unwrap_or(0);
+
+    // Whether every clause before step `end` and every one of the given
+    // literals can be satisfied together
+    let satisfiable = |end: usize, literals: &[Literal]| {
+        (0..1u32 << num_variables).any(|bits| {
+            let value = |literal: &Literal| {
+                let sign: bool = literal.sign().into();
+                (bits >> literal.var() & 1 == 1) == sign
+            };
+            steps[..end].iter().all(|step| match step {
+                Step::Clause(clause) => clause.iter().any(value),
+                Step::Assume(_) => true,
+            }) && literals.iter().all(value)
+        })
+    };
+
+    let queries = steps
+        .iter()
+        .enumerate()
+        .filter_map(|(idx, step)| match step {
+            Step::Assume(assumptions) => Some((idx, assumptions)),
+            Step::Clause(_) => None,
+        })
+        .collect::<Vec<_>>();
+    let mut answers = Vec::new();
+    solve(&icnf, |solution, failed| {
+        answers.push(match solution {
+            Solution::Sat(model) => Ok(model),
+            Solution::Unsat | Solution::Unknown => Err(failed.to_vec()),
+        })
+    })?;
+    assert_eq!(answers.len(), queries.len());
+
+    for ((end, assumptions), answer) in queries.into_iter().zip(answers) {
+        match answer {
+            Ok(model) => {
+                let literals = model
+                    .into_iter()
+                    .map(|(var, sign)| Literal::new(var, sign))
+                    .collect::<Vec<_>>();
+                assert!(satisfiable(end, &literals));
+                assert!(assumptions.iter().all(|literal| literals.contains(literal)));
+            }
+            Err(failed) => {
+                assert!(failed.iter().all(|literal| assumptions.contains(literal)));
+                assert!(!satisfiable(end, &failed));
+            }
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,41 @@
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc,
+};
+
+/// A handle for stopping a solver from another thread. Interrupting makes the
+/// solver's current (or next) solve call return `Solution::Unknown`.
+#[derive(Clone, Debug, Default)]
+pub struct Interrupt {
+    flag: Arc<AtomicBool>,
+}
+
+impl Interrupt {
+    pub fn interrupt(&self) {
+        self.flag.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_interrupted(&self) -> bool {
+        self.flag.load(Ordering::Relaxed)
+    }
+
+    /// Clears the flag, returning whether it was set
+    pub(crate) fn take(&self) -> bool {
+        self.flag.swap(false, Ordering::Relaxed)
+    }
+}
+
+#[test]
+fn interrupt() {
+    let interrupt = Interrupt::default();
+    let other = interrupt.clone();
+    assert!(!interrupt.is_interrupted());
+
+    std::thread::spawn(move || other.interrupt())
+        .join()
+        .unwrap();
+    assert!(interrupt.is_interrupted());
+    assert!(interrupt.take());
+    assert!(!interrupt.is_interrupted());
+    assert!(!interrupt.take());
+}
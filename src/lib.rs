@@ -1,28 +1,53 @@
 #![feature(cmp_min_max_by, is_sorted, vec_remove_item)]
 
+pub mod aiger;
 mod assignments;
+pub mod bmc;
+mod budget;
 mod conflict;
 mod counters;
+pub mod dimacs;
+mod error;
 pub mod formula;
+mod gauss;
 mod history;
+pub mod icnf;
+mod interrupt;
 mod literal;
 mod luby;
+pub mod maxsat;
+pub mod mcs;
+pub mod mus;
+pub mod opb;
+pub mod proof;
+pub mod qbf;
+pub mod qdimacs;
 mod sign;
+mod solutions;
 mod solver;
 mod watched;
+pub mod wcnf;
 
 use assignments::{Assignment, Assignments};
+pub use budget::{Budget, Statistics};
 use conflict::Conflict;
 use counters::Counters;
+pub use error::Error;
+use gauss::Gauss;
 use history::History;
-use literal::Literal;
+pub use interrupt::Interrupt;
+pub use literal::Literal;
 use luby::Luby;
 pub use sign::Sign;
+pub use solutions::Solutions;
 pub use solver::{Solution, Solver};
 use watched::Watched;
 
 pub type Variable = usize;
 type ClauseIdx = usize;
+/// Identifies a clause in a proof. The clauses of the original problem are
+/// numbered from 1 in the order they were added.
+pub type ClauseId = usize;
 type DecisionLevel = Variable;
 
 trait Evaluate {
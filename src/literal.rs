@@ -1,6 +1,6 @@
 use crate::{Assignments, Evaluate, Sign, Variable};
 
-#[derive(Clone, Copy, Eq, PartialEq, Ord, PartialOrd)]
+#[derive(Clone, Copy, Eq, Hash, PartialEq, Ord, PartialOrd)]
 pub struct Literal {
     code: Variable,
 }
@@ -60,6 +60,16 @@ impl From<isize> for Literal {
     }
 }
 
+impl From<Literal> for isize {
+    fn from(literal: Literal) -> Self {
+        let var = literal.var() as isize + 1;
+        match literal.sign() {
+            Sign::Positive => var,
+            Sign::Negative => -var,
+        }
+    }
+}
+
 impl From<usize> for Literal {
     fn from(code: usize) -> Literal {
         Self { code }
@@ -0,0 +1,311 @@
+use crate::{wcnf::Wcnf, Error, Literal, Sign, Solution, Solver, Variable};
+use std::collections::{BTreeMap, HashMap};
+
+/// An assignment satisfying every hard clause of a problem for which the
+/// total weight of the falsified soft clauses is as low as possible
+#[derive(Clone, Debug, PartialEq)]
+pub struct Optimum {
+    pub model: Vec<(Variable, Sign)>,
+    pub cost: u64,
+}
+
+/// Finds an optimal assignment of a weighted partial MaxSAT problem, or
+/// `None` if its hard clauses are unsatisfiable. `improved` is called with
+/// the cost of every assignment found that is better than the ones before
+/// it, the last of which is the optimum.
+///
+/// The search is core-guided, in the style of OLL: each soft clause is
+/// assumed to hold, and every unsatisfiable core of those assumptions raises
+/// the lower bound on the cost by the smallest weight in it. The core is
+/// then relaxed by a totalizer counting how many of its assumptions are
+/// violated, allowing one more of them to be violated for that weight.
+/// Assumptions are added in strata of decreasing weight, so that good
+/// assignments are found early.
+pub fn solve(wcnf: &Wcnf, mut improved: impl FnMut(u64)) -> Result<Option<Optimum>, Error> {
+    let mut search = Search::new(wcnf)?;
+    let mut best: Option<Optimum> = None;
+    let mut threshold = search.weights.values().copied().max().unwrap_or(0);
+
+    loop {
+        let assumptions = search
+            .weights
+            .iter()
+            .filter(|(_, weight)| **weight >= threshold)
+            .map(|(literal, _)| *literal)
+            .collect::<Vec<_>>();
+        match search.solver.solve_with_assumptions(&assumptions)? {
+            Solution::Sat(model) => {
+                let cost = cost(wcnf, &model);
+                if best.as_ref().is_none_or(|best| cost < best.cost) {
+                    improved(cost);
+                    best = Some(Optimum {
+                        model: model
+                            .into_iter()
+                            .filter(|(var, _)| *var < search.num_variables)
+                            .collect(),
+                        cost,
+                    });
+                }
+                if cost == search.lower_bound {
+                    return Ok(best);
+                }
+                // Move on to the next stratum, unless every assumption was
+                // already included, in which case the model is optimal
+                match search
+                    .weights
+                    .values()
+                    .copied()
+                    .filter(|weight| *weight < threshold)
+                    .max()
+                {
+                    Some(next) => threshold = next,
+                    None => return Ok(best),
+                }
+            }
+            Solution::Unsat => {
+                let core = search.solver.failed_assumptions().to_vec();
+                if core.is_empty() {
+                    return Ok(None);
+                }
+                search.relax(&core)?;
+            }
+            Solution::Unknown => return Err(Error::ResourceLimit),
+        }
+    }
+}
+
+/// The total weight of the soft clauses falsified by a model
+fn cost(wcnf: &Wcnf, model: &[(Variable, Sign)]) -> u64 {
+    let mut signs = vec![Sign::Negative; model.len()];
+    for (var, sign) in model {
+        signs[*var] = *sign;
+    }
+    wcnf.soft
+        .iter()
+        .filter(|(_, clause)| {
+            !clause
+                .iter()
+                .any(|literal| signs.get(literal.var()) == Some(&literal.sign()))
+        })
+        .map(|(weight, _)| weight)
+        .sum()
+}
+
+struct Search {
+    solver: Solver,
+    /// The number of variables of the problem, before any selectors
+    num_variables: usize,
+    /// The literals assumed to hold, along with the cost of violating each
+    weights: BTreeMap<Literal, u64>,
+    /// For assumptions that a totalizer's count stays below some bound, the
+    /// totalizer's outputs and the index of the assumed output
+    sums: HashMap<Literal, (usize, usize)>,
+    /// The outputs of each totalizer, where output `k` is implied by at least
+    /// `k + 1` of its inputs being true
+    totalizers: Vec<Vec<Literal>>,
+    /// The cost that every assignment is known to have
+    lower_bound: u64,
+}
+
+impl Search {
+    /// Adds the hard clauses to a solver and turns the soft clauses into
+    /// assumptions. Soft units are assumed directly; longer soft clauses get
+    /// a selector variable that implies them.
+    fn new(wcnf: &Wcnf) -> Result<Self, Error> {
+        let num_variables = wcnf
+            .hard
+            .iter()
+            .chain(wcnf.soft.iter().map(|(_, clause)| clause))
+            .flatten()
+            .map(|literal| literal.var() + 1)
+            .max()
+            .unwrap_or(0)
+            .max(wcnf.num_variables);
+        let mut solver = Solver::new();
+        solver.add_variables(num_variables);
+        for clause in wcnf.hard.iter() {
+            solver.add_clause(clause)?;
+        }
+
+        let mut weights = BTreeMap::new();
+        let mut lower_bound = 0;
+        for (weight, clause) in wcnf.soft.iter() {
+            let assumption = match clause.as_slice() {
+                _ if *weight == 0 => continue,
+                [] => {
+                    lower_bound += weight;
+                    continue;
+                }
+                [literal] => *literal,
+                _ => {
+                    let selector = Literal::new(solver.new_variable(), Sign::Positive);
+                    let mut guarded = clause.clone();
+                    guarded.push(!selector);
+                    solver.add_clause(&guarded)?;
+                    selector
+                }
+            };
+            *weights.entry(assumption).or_insert(0) += weight;
+        }
+
+        Ok(Self {
+            solver,
+            num_variables,
+            weights,
+            sums: HashMap::new(),
+            totalizers: Vec::new(),
+            lower_bound,
+        })
+    }
+
+    /// Pays for a core of assumptions that can't all hold: every assumption
+    /// in it gets cheaper by the smallest weight among them, and violating
+    /// one more of them than before costs that weight instead
+    fn relax(&mut self, core: &[Literal]) -> Result<(), Error> {
+        let cheapest = core
+            .iter()
+            .map(|literal| self.weights[literal])
+            .min()
+            .unwrap_or(0);
+        self.lower_bound += cheapest;
+        for literal in core {
+            let weight = self.weights.get_mut(literal).unwrap();
+            *weight -= cheapest;
+            if *weight == 0 {
+                self.weights.remove(literal);
+            }
+            // A bound on a sum that can't hold is loosened by one
+            if let Some((totalizer, output)) = self.sums.get(literal).copied() {
+                if let Some(next) = self.totalizers[totalizer].get(output + 1) {
+                    self.assume_sum(!*next, totalizer, output + 1, cheapest);
+                }
+            }
+        }
+
+        if let [literal] = core {
+            return self.solver.add_clause(&[!*literal]);
+        }
+        let violated = core.iter().map(|literal| !*literal).collect::<Vec<_>>();
+        let outputs = self.totalize(&violated)?;
+        self.totalizers.push(outputs);
+        let totalizer = self.totalizers.len() - 1;
+        let at_most_one = !self.totalizers[totalizer][1];
+        self.assume_sum(at_most_one, totalizer, 1, cheapest);
+        Ok(())
+    }
+
+    fn assume_sum(&mut self, literal: Literal, totalizer: usize, output: usize, weight: u64) {
+        *self.weights.entry(literal).or_insert(0) += weight;
+        self.sums.insert(literal, (totalizer, output));
+    }
+
+    /// Builds a totalizer over some literals, returning its outputs. Only the
+    /// clauses forcing the outputs up are needed, since the outputs are only
+    /// ever assumed false.
+    fn totalize(&mut self, inputs: &[Literal]) -> Result<Vec<Literal>, Error> {
+        if inputs.len() == 1 {
+            return Ok(inputs.to_vec());
+        }
+        let (left, right) = inputs.split_at(inputs.len() / 2);
+        let (left, right) = (self.totalize(left)?, self.totalize(right)?);
+        let outputs = self
+            .solver
+            .add_variables(inputs.len())
+            .map(|var| Literal::new(var, Sign::Positive))
+            .collect::<Vec<_>>();
+        for i in 0..=left.len() {
+            for j in 0..=right.len() {
+                if i + j == 0 {
+                    continue;
+                }
+                let mut clause = vec![outputs[i + j - 1]];
+                if i > 0 {
+                    clause.push(!left[i - 1]);
+                }
+                if j > 0 {
+                    clause.push(!right[j - 1]);
+                }
+                self.solver.add_clause(&clause)?;
+            }
+        }
+        Ok(outputs)
+    }
+}
+
+#[cfg(test)]
+fn brute_force(wcnf: &Wcnf) -> Option<u64> {
+    (0..1u32 << wcnf.num_variables)
+        .map(|bits| {
+            (0..wcnf.num_variables)
+                .map(|var| match bits >> var & 1 {
+                    1 => (var, Sign::Positive),
+                    _ => (var, Sign::Negative),
+                })
+                .collect::<Vec<_>>()
+        })
+        .filter(|model| {
+            wcnf.hard.iter().all(|clause| {
+                clause
+                    .iter()
+                    .any(|literal| model[literal.var()].1 == literal.sign())
+            })
+        })
+        .map(|model| cost(wcnf, &model))
+        .min()
+}
+
+#[test]
+fn weighted() -> Result<(), Error> {
+    let wcnf = crate::wcnf::parse([
+        "h 1 2 3 0",
+        "h -1 -2 0",
+        "5 -1 0",
+        "4 -2 0",
+        "3 -3 0",
+        "2 1 0",
+        "1 2 3 0",
+    ])?;
+    let mut costs = Vec::new();
+    let optimum = solve(&wcnf, |cost| costs.push(cost))?.expect("Expected an optimum");
+    assert_eq!(optimum.cost, 5);
+    assert_eq!(cost(&wcnf, &optimum.model), 5);
+    assert_eq!(costs.last(), Some(&5));
+    assert!(costs.windows(2).all(|pair| pair[0] > pair[1]));
+
+    let unsat = crate::wcnf::parse(["h 1 0", "h -1 0", "1 2 0"])?;
+    assert_eq!(solve(&unsat, |_| {})?, None);
+    Ok(())
+}
+
+#[cfg(test)]
+impl quickcheck::Arbitrary for Wcnf {
+    /// A problem small enough to solve by brute force
+    fn arbitrary<G: quickcheck::Gen>(g: &mut G) -> Self {
+        use rand::Rng;
+
+        let num_variables = g.gen_range(1, 8);
+        let clause = |g: &mut G| {
+            (0..g.gen_range(0, 4))
+                .map(|_| Literal::new(g.gen_range(0, num_variables), g.gen::<bool>()))
+                .collect::<Vec<_>>()
+        };
+        Wcnf {
+            num_variables,
+            hard: (0..g.gen_range(0, 6)).map(|_| clause(g)).collect(),
+            soft: (0..g.gen_range(0, 12))
+                .map(|_| (g.gen_range(0, 10), clause(g)))
+                .collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+#[quickcheck_macros::quickcheck]
+fn matches_brute_force(wcnf: Wcnf) -> Result<(), Error> {
+    let optimum = solve(&wcnf, |_| {})?;
+    if let Some(optimum) = &optimum {
+        assert_eq!(cost(&wcnf, &optimum.model), optimum.cost);
+    }
+    assert_eq!(optimum.map(|optimum| optimum.cost), brute_force(&wcnf));
+    Ok(())
+}
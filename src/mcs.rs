@@ -0,0 +1,147 @@
+use crate::{
+    dimacs::Cnf,
+    mus::{guarded, satisfies},
+    Error, Literal, Sign, Solution, Solver, Variable,
+};
+
+/// An iterator over the minimal correction subsets of a problem: the subsets
+/// of its clauses whose removal makes the rest satisfiable, but no smaller
+/// subset of which does. Each is given as the indices of its clauses in
+/// ascending order. A satisfiable problem has just the empty one.
+///
+/// Every candidate is checked with the same solver, in which each clause is
+/// guarded by a selector variable. A correction subset is found as the
+/// complement of a maximal satisfiable subset, grown one clause at a time
+/// using assumptions, and is then blocked by requiring that at least one of
+/// its clauses is kept.
+pub struct Corrections<'a> {
+    cnf: &'a Cnf,
+    solver: Solver,
+    selectors: Vec<Variable>,
+    done: bool,
+}
+
+impl<'a> Corrections<'a> {
+    pub fn new(cnf: &'a Cnf) -> Result<Self, Error> {
+        let (solver, selectors) = guarded(cnf)?;
+        Ok(Self {
+            cnf,
+            solver,
+            selectors,
+            done: false,
+        })
+    }
+
+    fn next_correction(&mut self) -> Result<Option<Vec<usize>>, Error> {
+        let mut kept = vec![false; self.cnf.clauses.len()];
+        if !self.keep_satisfied(&mut kept)? {
+            return Ok(None);
+        }
+        for idx in 0..kept.len() {
+            if !kept[idx] {
+                kept[idx] = true;
+                if !self.keep_satisfied(&mut kept)? {
+                    kept[idx] = false;
+                }
+            }
+        }
+
+        let correction = (0..kept.len())
+            .filter(|idx| !kept[*idx])
+            .collect::<Vec<_>>();
+        let blocking = correction
+            .iter()
+            .map(|idx| Literal::new(self.selectors[*idx], Sign::Positive))
+            .collect::<Vec<_>>();
+        self.solver.add_clause(&blocking)?;
+        Ok(Some(correction))
+    }
+
+    /// Looks for a model satisfying every kept clause, and keeps every other
+    /// clause it satisfies as well. Returns whether there was one.
+    fn keep_satisfied(&mut self, kept: &mut [bool]) -> Result<bool, Error> {
+        let assumptions = (0..kept.len())
+            .filter(|idx| kept[*idx])
+            .map(|idx| Literal::new(self.selectors[idx], Sign::Positive))
+            .collect::<Vec<_>>();
+        match self.solver.solve_with_assumptions(&assumptions)? {
+            Solution::Unsat => Ok(false),
+            Solution::Unknown => Err(Error::ResourceLimit),
+            Solution::Sat(assignment) => {
+                let mut model = vec![Sign::Positive; self.solver.num_variables()];
+                for (var, sign) in assignment {
+                    model[var] = sign;
+                }
+                for (idx, clause) in self.cnf.clauses.iter().enumerate() {
+                    kept[idx] |= clause.iter().any(|literal| satisfies(&model, *literal));
+                }
+                Ok(true)
+            }
+        }
+    }
+}
+
+impl<'a> Iterator for Corrections<'a> {
+    type Item = Result<Vec<usize>, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        let correction = self.next_correction().transpose();
+        self.done = !matches!(correction, Some(Ok(_)));
+        correction
+    }
+}
+
+#[test]
+fn corrections() -> Result<(), Error> {
+    let literal = |x: isize| Literal::from(x);
+    let cnf = Cnf {
+        num_variables: 2,
+        clauses: vec![
+            vec![literal(1)],
+            vec![literal(-1)],
+            vec![literal(2)],
+            vec![literal(-2), literal(1)],
+        ],
+        xors: Vec::new(),
+    };
+    let mut corrections = Corrections::new(&cnf)?.collect::<Result<Vec<_>, _>>()?;
+    corrections.sort();
+    assert_eq!(corrections, vec![vec![0, 2], vec![0, 3], vec![1]]);
+
+    let satisfiable = Cnf {
+        num_variables: 2,
+        clauses: vec![cnf.clauses[0].clone(), cnf.clauses[2].clone()],
+        xors: Vec::new(),
+    };
+    let corrections = Corrections::new(&satisfiable)?.collect::<Result<Vec<_>, _>>()?;
+    assert_eq!(corrections, vec![Vec::<usize>::new()]);
+    Ok(())
+}
+
+#[test]
+fn minimal_corrections() -> Result<(), Error> {
+    let cnf = crate::dimacs::parse_file("inputs/aim-100.cnf")?;
+    let satisfiable = |removed: &[usize]| -> Result<bool, Error> {
+        let mut solver = Solver::new();
+        for (idx, clause) in cnf.clauses.iter().enumerate() {
+            if !removed.contains(&idx) {
+                solver.add_clause(clause)?;
+            }
+        }
+        Ok(matches!(solver.solve()?, Solution::Sat(_)))
+    };
+    for correction in Corrections::new(&cnf)?.take(10) {
+        let correction = correction?;
+        assert!(!correction.is_empty());
+        assert!(satisfiable(&correction)?);
+        for idx in 0..correction.len() {
+            let mut smaller = correction.clone();
+            smaller.remove(idx);
+            assert!(!satisfiable(&smaller)?);
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,230 @@
+use crate::{dimacs::Cnf, Error, Literal, Sign, Solution, Solver, Variable};
+use fixedbitset::FixedBitSet;
+
+/// Finds a minimal unsatisfiable subset of a problem's clauses: an
+/// unsatisfiable subset from which no clause can be removed without making
+/// it satisfiable. Returns the indices of its clauses, or `None` if the
+/// problem is satisfiable.
+///
+/// Each clause is guarded by a selector variable so that a single solver can
+/// try out every subset using assumptions. Clauses are removed one at a time,
+/// and whenever the rest is still unsatisfiable the subset shrinks to the
+/// clauses whose selectors failed. When removing a clause makes the rest
+/// satisfiable, the clause is needed, and so is any clause that the model
+/// can be rotated onto by flipping one variable.
+pub fn extract(cnf: &Cnf) -> Result<Option<Vec<usize>>, Error> {
+    let mut shrinker = Shrinker::new(cnf)?;
+    let all = (0..cnf.clauses.len()).collect::<Vec<_>>();
+    let mut subset = match shrinker.core(&all)? {
+        Some(core) => core,
+        None => return Ok(None),
+    };
+
+    while let Some(candidate) = subset
+        .iter()
+        .copied()
+        .find(|idx| !shrinker.needed.contains(*idx))
+    {
+        let rest = subset
+            .iter()
+            .copied()
+            .filter(|idx| *idx != candidate)
+            .collect::<Vec<_>>();
+        match shrinker.core(&rest)? {
+            Some(core) => subset = core,
+            None => {
+                subset.sort_unstable();
+                shrinker.rotate(candidate, &subset);
+            }
+        }
+    }
+
+    subset.sort_unstable();
+    Ok(Some(subset))
+}
+
+struct Shrinker<'a> {
+    cnf: &'a Cnf,
+    solver: Solver,
+    selectors: Vec<Variable>,
+    /// Clauses known to be in every unsatisfiable subset of the current one
+    needed: FixedBitSet,
+    /// The last model found, indexed by variable
+    model: Vec<Sign>,
+    /// The clauses containing each literal
+    occurrences: Vec<Vec<usize>>,
+}
+
+impl<'a> Shrinker<'a> {
+    fn new(cnf: &'a Cnf) -> Result<Self, Error> {
+        let (solver, selectors) = guarded(cnf)?;
+        let num_variables = solver.num_variables() - selectors.len();
+        let mut occurrences = vec![Vec::new(); 2 * num_variables];
+        for (idx, clause) in cnf.clauses.iter().enumerate() {
+            for literal in clause {
+                occurrences[literal.code()].push(idx);
+            }
+        }
+
+        Ok(Self {
+            cnf,
+            solver,
+            selectors,
+            needed: FixedBitSet::with_capacity(cnf.clauses.len()),
+            model: vec![Sign::Positive; num_variables],
+            occurrences,
+        })
+    }
+
+    /// Solves with only the given clauses, returning the subset of them that
+    /// was needed to show they're unsatisfiable, or `None` if they aren't.
+    /// The model found is kept for rotation.
+    fn core(&mut self, clauses: &[usize]) -> Result<Option<Vec<usize>>, Error> {
+        let assumptions = clauses
+            .iter()
+            .map(|idx| Literal::new(self.selectors[*idx], Sign::Positive))
+            .collect::<Vec<_>>();
+        match self.solver.solve_with_assumptions(&assumptions)? {
+            Solution::Unsat => {
+                let first = self.selectors.first().copied().unwrap_or(0);
+                Ok(Some(
+                    self.solver
+                        .failed_assumptions()
+                        .iter()
+                        .map(|literal| literal.var() - first)
+                        .collect(),
+                ))
+            }
+            Solution::Sat(model) => {
+                for (var, sign) in model {
+                    if var < self.model.len() {
+                        self.model[var] = sign;
+                    }
+                }
+                Ok(None)
+            }
+            Solution::Unknown => Err(Error::ResourceLimit),
+        }
+    }
+
+    /// Marks `clause` as needed, given that the last model satisfies every
+    /// other clause of `subset` (which must be sorted). Flipping a variable
+    /// of the clause may falsify exactly one other clause instead, which is
+    /// then needed too.
+    fn rotate(&mut self, clause: usize, subset: &[usize]) {
+        self.needed.insert(clause);
+        let mut pending = vec![(clause, self.model.clone())];
+        while let Some((clause, model)) = pending.pop() {
+            for literal in self.cnf.clauses[clause].iter() {
+                // The literal is false, so the clauses containing its
+                // negation are the only ones flipping it could falsify
+                let falsified = self.occurrences[(!*literal).code()]
+                    .iter()
+                    .copied()
+                    .filter(|other| subset.binary_search(other).is_ok())
+                    .filter(|other| {
+                        self.cnf.clauses[*other].iter().all(|x| {
+                            if x.var() == literal.var() {
+                                x != literal
+                            } else {
+                                !satisfies(&model, *x)
+                            }
+                        })
+                    })
+                    .collect::<Vec<_>>();
+                if let [other] = falsified.as_slice() {
+                    if !self.needed.put(*other) {
+                        let mut rotated = model.clone();
+                        rotated[literal.var()] = literal.sign();
+                        pending.push((*other, rotated));
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Creates a solver for a problem in which every clause is guarded by its own
+/// selector variable, so that it only has to be satisfied when its selector
+/// is true. Selectors are numbered consecutively after the problem's
+/// variables.
+pub(crate) fn guarded(cnf: &Cnf) -> Result<(Solver, Vec<Variable>), Error> {
+    if !cnf.xors.is_empty() {
+        return Err(Error::InvalidInput(
+            "Subsets of problems with XOR constraints aren't supported".to_owned(),
+        ));
+    }
+    let num_variables = cnf
+        .clauses
+        .iter()
+        .flatten()
+        .map(|literal| literal.var() + 1)
+        .max()
+        .unwrap_or(0)
+        .max(cnf.num_variables);
+    let mut solver = Solver::new();
+    solver.add_variables(num_variables);
+
+    let mut selectors = Vec::with_capacity(cnf.clauses.len());
+    for clause in cnf.clauses.iter() {
+        let selector = solver.new_variable();
+        let mut guarded = clause.clone();
+        guarded.push(Literal::new(selector, Sign::Negative));
+        solver.add_clause(&guarded)?;
+        selectors.push(selector);
+    }
+    Ok((solver, selectors))
+}
+
+pub(crate) fn satisfies(model: &[Sign], literal: Literal) -> bool {
+    model[literal.var()] == literal.sign()
+}
+
+#[cfg(test)]
+fn is_unsat(cnf: &Cnf, subset: &[usize]) -> Result<bool, Error> {
+    let mut solver = Solver::new();
+    for idx in subset {
+        solver.add_clause(&cnf.clauses[*idx])?;
+    }
+    Ok(matches!(solver.solve()?, Solution::Unsat))
+}
+
+#[test]
+fn minimal() -> Result<(), Error> {
+    let cnf = crate::dimacs::parse_file("inputs/aim-100.cnf")?;
+    let mus = extract(&cnf)?.expect("Expected Unsat");
+    assert!(is_unsat(&cnf, &mus)?);
+    for removed in mus.iter() {
+        let rest = mus
+            .iter()
+            .copied()
+            .filter(|idx| idx != removed)
+            .collect::<Vec<_>>();
+        assert!(!is_unsat(&cnf, &rest)?);
+    }
+    Ok(())
+}
+
+#[test]
+fn trivial() -> Result<(), Error> {
+    let literal = |x: isize| Literal::from(x);
+    let cnf = Cnf {
+        num_variables: 2,
+        clauses: vec![
+            vec![literal(1), literal(2)],
+            vec![literal(-1)],
+            vec![],
+            vec![literal(-2)],
+        ],
+        xors: Vec::new(),
+    };
+    assert_eq!(extract(&cnf)?, Some(vec![2]));
+
+    let satisfiable = Cnf {
+        num_variables: 2,
+        clauses: cnf.clauses[..2].to_vec(),
+        xors: Vec::new(),
+    };
+    assert_eq!(extract(&satisfiable)?, None);
+    Ok(())
+}
@@ -0,0 +1,527 @@
+use crate::{
+    dimacs::{open, read_lines, tokens, ParseError, ParseErrorKind, MAX_VARIABLE},
+    Error, Literal, Sign, Solution, Solver, Variable,
+};
+use std::{
+    convert::TryFrom,
+    fmt::{self, Formatter},
+    io::Read,
+    path::Path,
+};
+
+/// A pseudo-Boolean problem, as read from an OPB file
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Opb {
+    pub num_variables: usize,
+    /// The terms of the sum to minimize, if there is one
+    pub objective: Option<Vec<(i64, Literal)>>,
+    pub constraints: Vec<Constraint>,
+}
+
+/// A linear constraint on the sum of the coefficients of the true literals
+#[derive(Clone, Debug, PartialEq)]
+pub struct Constraint {
+    pub terms: Vec<(i64, Literal)>,
+    pub relation: Relation,
+    pub rhs: i64,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Relation {
+    AtLeast,
+    AtMost,
+    Equal,
+}
+
+/// The ways in which an OPB file can fail to be valid
+#[derive(Clone, Debug, PartialEq)]
+pub enum ErrorKind {
+    /// A term doesn't start with an integer coefficient, or a constraint's
+    /// right-hand side isn't an integer
+    InvalidCoefficient,
+    /// A coefficient isn't followed by a variable such as `x1` or `~x1`
+    InvalidVariable,
+    /// A constraint ends without a `>=`, `<=` or `=`
+    MissingRelation,
+    /// A constraint or objective isn't terminated by a `;`
+    UnterminatedConstraint,
+}
+
+impl ErrorKind {
+    pub(crate) fn describe(&self, f: &mut Formatter, found: &str) -> fmt::Result {
+        match self {
+            ErrorKind::InvalidCoefficient => write!(f, "expected an integer, found {}", found),
+            ErrorKind::InvalidVariable => write!(
+                f,
+                "expected a variable such as `x1` or `~x1`, found {}",
+                found
+            ),
+            ErrorKind::MissingRelation => {
+                write!(f, "expected `>=`, `<=` or `=`, found {}", found)
+            }
+            ErrorKind::UnterminatedConstraint => {
+                write!(f, "expected a `;` ending the constraint, found {}", found)
+            }
+        }
+    }
+}
+
+/// Terms with non-negative coefficients along with the degree their sum has
+/// to reach, as taken by `Solver::add_constraint`
+pub type Normalized = (Vec<(u64, Literal)>, u64);
+
+impl Constraint {
+    /// The constraint as one, or for an equality two, normalized
+    /// constraints. A negative coefficient is made positive by negating its
+    /// literal, and an upper bound becomes a lower bound on the negated
+    /// literals.
+    pub fn normalize(&self) -> Result<Vec<Normalized>, Error> {
+        let at_least = |sign: i128| -> Result<Normalized, Error> {
+            let mut degree = sign * i128::from(self.rhs);
+            let mut terms = Vec::with_capacity(self.terms.len());
+            for (coefficient, literal) in self.terms.iter() {
+                let coefficient = sign * i128::from(*coefficient);
+                if coefficient < 0 {
+                    degree -= coefficient;
+                    terms.push((coefficient.unsigned_abs(), !*literal));
+                } else {
+                    terms.push((coefficient.unsigned_abs(), *literal));
+                }
+            }
+            let too_large = || Error::InvalidInput("Coefficients are too large".to_owned());
+            let terms = terms
+                .into_iter()
+                .map(|(coefficient, literal)| Ok((u64::try_from(coefficient)?, literal)))
+                .collect::<Result<Vec<_>, std::num::TryFromIntError>>()
+                .map_err(|_| too_large())?;
+            let degree = u64::try_from(degree.max(0)).map_err(|_| too_large())?;
+            Ok((terms, degree))
+        };
+        match self.relation {
+            Relation::AtLeast => Ok(vec![at_least(1)?]),
+            Relation::AtMost => Ok(vec![at_least(-1)?]),
+            Relation::Equal => Ok(vec![at_least(1)?, at_least(-1)?]),
+        }
+    }
+}
+
+impl Opb {
+    /// The value of the objective under a model, if there is an objective
+    pub fn cost(&self, model: &[(Variable, Sign)]) -> Option<i64> {
+        let objective = self.objective.as_ref()?;
+        let mut signs = vec![Sign::Negative; model.len()];
+        for (var, sign) in model {
+            signs[*var] = *sign;
+        }
+        Some(
+            objective
+                .iter()
+                .filter(|(_, literal)| signs.get(literal.var()) == Some(&literal.sign()))
+                .map(|(coefficient, _)| coefficient)
+                .sum(),
+        )
+    }
+}
+
+/// Finds an assignment satisfying every constraint of a problem, or `None`
+/// if there is none. If the problem has an objective, the assignment
+/// minimizes it, and `improved` is called with the value of the objective
+/// under every better assignment found on the way.
+///
+/// Each assignment found is improved on by adding the constraint that the
+/// objective be smaller, until that can't be satisfied.
+pub fn solve(
+    opb: &Opb,
+    mut improved: impl FnMut(i64),
+) -> Result<Option<Vec<(Variable, Sign)>>, Error> {
+    let mut solver = Solver::new();
+    solver.add_opb(opb)?;
+    let mut best = None;
+    loop {
+        match solver.solve()? {
+            Solution::Sat(model) => {
+                let model = model
+                    .into_iter()
+                    .filter(|(var, _)| *var < opb.num_variables)
+                    .collect::<Vec<_>>();
+                let (objective, cost) = match (&opb.objective, opb.cost(&model)) {
+                    (Some(objective), Some(cost)) => (objective, cost),
+                    _ => return Ok(Some(model)),
+                };
+                improved(cost);
+                best = Some(model);
+                let rhs = match cost.checked_sub(1) {
+                    Some(rhs) => rhs,
+                    None => return Ok(best),
+                };
+                let bound = Constraint {
+                    terms: objective.clone(),
+                    relation: Relation::AtMost,
+                    rhs,
+                };
+                for (terms, degree) in bound.normalize()? {
+                    solver.add_constraint(&terms, degree)?;
+                }
+            }
+            Solution::Unsat => return Ok(best),
+            Solution::Unknown => return Err(Error::ResourceLimit),
+        }
+    }
+}
+
+pub fn parse_file(path: impl AsRef<Path>) -> Result<Opb, Error> {
+    read(open(path.as_ref())?)
+}
+
+/// Reads a problem in OPB format, decompressing it as in `dimacs::read`
+pub fn read(reader: impl Read) -> Result<Opb, Error> {
+    let mut parser = Parser::default();
+    read_lines(reader, |line| parser.parse_line(line))?;
+    parser.finish()
+}
+
+/// Parses a problem in the OPB format of the pseudo-Boolean competitions.
+/// Each constraint, such as `+2 x1 -3 ~x2 >= 1 ;`, is a sum of terms made of
+/// an integer coefficient and a variable (`~` negating it), a relation of
+/// `>=`, `<=` or `=`, and an integer. An objective to minimize, such as
+/// `min: +1 x1 +1 x2 ;`, may come first. Lines starting with `*` are
+/// comments, but the number of variables is taken from a
+/// `* #variable= <n>` line if there is one.
+pub fn parse(lines: impl IntoIterator<Item = impl AsRef<str>>) -> Result<Opb, Error> {
+    let mut parser = Parser::default();
+    for line in lines {
+        parser.parse_line(line.as_ref())?;
+    }
+    parser.finish()
+}
+
+#[derive(Default)]
+struct Parser {
+    opb: Opb,
+    /// The number of the last line parsed
+    line: usize,
+    /// The constraint or objective being parsed
+    statement: Option<Statement>,
+}
+
+#[derive(Default)]
+struct Statement {
+    objective: bool,
+    terms: Vec<(i64, Literal)>,
+    /// The coefficient of a term whose variable hasn't been parsed yet
+    coefficient: Option<i64>,
+    relation: Option<Relation>,
+    rhs: Option<i64>,
+}
+
+impl Parser {
+    fn parse_line(&mut self, line: &str) -> Result<(), Error> {
+        self.line += 1;
+        let tokens = tokens(line);
+        if let Some((_, token)) = tokens.first() {
+            if token.starts_with('*') {
+                self.parse_comment(&tokens);
+                return Ok(());
+            }
+        }
+
+        let number = self.line;
+        for (column, token) in tokens
+            .into_iter()
+            .flat_map(|(column, token)| split(column, token))
+        {
+            let error = |kind| ParseError {
+                kind: ParseErrorKind::Opb(kind),
+                line: number,
+                column,
+                token: token.to_owned(),
+            };
+            let statement = match &mut self.statement {
+                Some(statement) => statement,
+                None if token == "min:" => {
+                    self.statement = Some(Statement {
+                        objective: true,
+                        ..Statement::default()
+                    });
+                    continue;
+                }
+                None => self.statement.get_or_insert_with(Statement::default),
+            };
+
+            if token == ";" {
+                let statement = self.statement.take().unwrap_or_default();
+                self.end(statement).map_err(error)?;
+            } else if statement.rhs.is_some() {
+                return Err(error(ErrorKind::UnterminatedConstraint).into());
+            } else if statement.relation.is_some() {
+                let rhs = token
+                    .parse()
+                    .map_err(|_| error(ErrorKind::InvalidCoefficient))?;
+                statement.rhs = Some(rhs);
+            } else if let Some(coefficient) = statement.coefficient.take() {
+                let literal = variable(token).ok_or_else(|| error(ErrorKind::InvalidVariable))?;
+                self.opb.num_variables = self.opb.num_variables.max(literal.var() + 1);
+                statement.terms.push((coefficient, literal));
+            } else {
+                match (token, statement.objective) {
+                    (">=", false) => statement.relation = Some(Relation::AtLeast),
+                    ("<=", false) => statement.relation = Some(Relation::AtMost),
+                    ("=", false) => statement.relation = Some(Relation::Equal),
+                    _ => {
+                        let coefficient = token
+                            .parse()
+                            .map_err(|_| error(ErrorKind::InvalidCoefficient))?;
+                        statement.coefficient = Some(coefficient);
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Takes the number of variables from a `* #variable= <n>` comment, unless
+    /// it's too large to be believed
+    fn parse_comment(&mut self, tokens: &[(usize, &str)]) {
+        let declared = tokens
+            .windows(2)
+            .find(|pair| pair[0].1 == "#variable=")
+            .and_then(|pair| pair[1].1.parse::<usize>().ok())
+            .filter(|declared| *declared <= MAX_VARIABLE);
+        if let Some(declared) = declared {
+            self.opb.num_variables = self.opb.num_variables.max(declared);
+        }
+    }
+
+    /// Adds a statement ended by a `;`, returning what's missing from it if
+    /// it's incomplete
+    fn end(&mut self, statement: Statement) -> Result<(), ErrorKind> {
+        if statement.coefficient.is_some() {
+            return Err(ErrorKind::InvalidVariable);
+        }
+        if statement.objective {
+            self.opb.objective = Some(statement.terms);
+            return Ok(());
+        }
+        let relation = statement.relation.ok_or(ErrorKind::MissingRelation)?;
+        let rhs = statement.rhs.ok_or(ErrorKind::InvalidCoefficient)?;
+        self.opb.constraints.push(Constraint {
+            terms: statement.terms,
+            relation,
+            rhs,
+        });
+        Ok(())
+    }
+
+    fn finish(self) -> Result<Opb, Error> {
+        match self.statement {
+            Some(_) => {
+                let kind = ParseErrorKind::Opb(ErrorKind::UnterminatedConstraint);
+                Err(ParseError::at_end(kind, self.line + 1).into())
+            }
+            None => Ok(self.opb),
+        }
+    }
+}
+
+/// Splits the `;`s off of a token, since they needn't be separated from what
+/// comes before them
+fn split(column: usize, token: &str) -> Vec<(usize, &str)> {
+    let mut pieces = Vec::new();
+    let (mut column, mut rest) = (column, token);
+    while let Some(idx) = rest.find(';') {
+        if idx > 0 {
+            pieces.push((column, &rest[..idx]));
+            column += rest[..idx].chars().count();
+        }
+        pieces.push((column, ";"));
+        column += 1;
+        rest = &rest[idx + 1..];
+    }
+    if !rest.is_empty() {
+        pieces.push((column, rest));
+    }
+    pieces
+}
+
+/// Parses a variable such as `x3`, or its negation `~x3`
+fn variable(token: &str) -> Option<Literal> {
+    let (sign, name) = match token.strip_prefix('~') {
+        Some(name) => (-1, name),
+        None => (1, token),
+    };
+    let number = name.strip_prefix('x')?.parse::<isize>().ok()?;
+    if number < 1 || number as usize > MAX_VARIABLE {
+        return None;
+    }
+    Some(Literal::from(sign * number))
+}
+
+#[test]
+fn parse_opb() -> Result<(), Error> {
+    let literal = |x: isize| Literal::from(x);
+    let opb = parse([
+        "* #variable= 4 #constraint= 3",
+        "min: +1 x1 -2 ~x2 ;",
+        "+2 x1 +3 x2 >= 4 ;",
+        "-1 x1 +1 ~x3",
+        "  = 0; 1 x1 1 x2 <= 1;",
+    ])?;
+    assert_eq!(
+        opb,
+        Opb {
+            num_variables: 4,
+            objective: Some(vec![(1, literal(1)), (-2, literal(-2))]),
+            constraints: vec![
+                Constraint {
+                    terms: vec![(2, literal(1)), (3, literal(2))],
+                    relation: Relation::AtLeast,
+                    rhs: 4,
+                },
+                Constraint {
+                    terms: vec![(-1, literal(1)), (1, literal(-3))],
+                    relation: Relation::Equal,
+                    rhs: 0,
+                },
+                Constraint {
+                    terms: vec![(1, literal(1)), (1, literal(2))],
+                    relation: Relation::AtMost,
+                    rhs: 1,
+                },
+            ],
+        }
+    );
+
+    // -x1 + ~x3 = 0 means ~x1 + ~x3 >= 1 and x1 + x3 >= 1
+    assert_eq!(
+        opb.constraints[1].normalize()?,
+        vec![
+            (vec![(1, literal(-1)), (1, literal(-3))], 1),
+            (vec![(1, literal(1)), (1, literal(3))], 1),
+        ]
+    );
+    Ok(())
+}
+
+#[test]
+fn opb_errors() {
+    use crate::dimacs::parse_error;
+    let error = |lines: &[&str]| parse_error(parse(lines));
+    assert_eq!(
+        error(&["+1 y1 >= 1 ;"]),
+        (
+            ParseErrorKind::Opb(ErrorKind::InvalidVariable),
+            1,
+            4,
+            "y1".to_owned()
+        )
+    );
+    assert_eq!(
+        error(&["+1 x4000000000000 >= 1 ;"]),
+        (
+            ParseErrorKind::Opb(ErrorKind::InvalidVariable),
+            1,
+            4,
+            "x4000000000000".to_owned()
+        )
+    );
+    assert_eq!(
+        error(&["+1 x1 +2 x2 ;"]),
+        (
+            ParseErrorKind::Opb(ErrorKind::MissingRelation),
+            1,
+            13,
+            ";".to_owned()
+        )
+    );
+    assert_eq!(
+        error(&["+1 x1 x2 >= 1 ;"]),
+        (
+            ParseErrorKind::Opb(ErrorKind::InvalidCoefficient),
+            1,
+            7,
+            "x2".to_owned()
+        )
+    );
+    assert_eq!(
+        error(&["+1 x1 >= 1 2 ;"]),
+        (
+            ParseErrorKind::Opb(ErrorKind::UnterminatedConstraint),
+            1,
+            12,
+            "2".to_owned()
+        )
+    );
+    assert_eq!(
+        error(&["+1 x1 >= 1", ""]),
+        (
+            ParseErrorKind::Opb(ErrorKind::UnterminatedConstraint),
+            3,
+            1,
+            String::new()
+        )
+    );
+}
+
+#[cfg(test)]
+impl quickcheck::Arbitrary for Opb {
+    /// A problem small enough to solve by brute force
+    fn arbitrary<G: quickcheck::Gen>(g: &mut G) -> Self {
+        use rand::Rng;
+
+        let num_variables = g.gen_range(1, 9);
+        let terms = |g: &mut G| {
+            (0..g.gen_range(1, 6))
+                .map(|_| {
+                    let literal = Literal::new(g.gen_range(0, num_variables), g.gen::<bool>());
+                    (g.gen_range(-4, 6), literal)
+                })
+                .collect::<Vec<_>>()
+        };
+        let relations = [Relation::AtLeast, Relation::AtMost, Relation::Equal];
+        Opb {
+            num_variables,
+            objective: g.gen::<bool>().then(|| terms(g)),
+            constraints: (0..g.gen_range(0, 8))
+                .map(|_| Constraint {
+                    terms: terms(g),
+                    relation: relations[g.gen_range(0, 3)],
+                    rhs: g.gen_range(-3, 8),
+                })
+                .collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+#[quickcheck_macros::quickcheck]
+fn matches_brute_force(opb: Opb) -> Result<(), Error> {
+    let holds = |constraint: &Constraint, model: &[(Variable, Sign)]| {
+        let sum = constraint
+            .terms
+            .iter()
+            .filter(|(_, literal)| model[literal.var()].1 == literal.sign())
+            .map(|(coefficient, _)| coefficient)
+            .sum::<i64>();
+        match constraint.relation {
+            Relation::AtLeast => sum >= constraint.rhs,
+            Relation::AtMost => sum <= constraint.rhs,
+            Relation::Equal => sum == constraint.rhs,
+        }
+    };
+    let best = (0..1u32 << opb.num_variables)
+        .map(|bits| {
+            (0..opb.num_variables)
+                .map(|var| (var, (bits >> var & 1 == 1).into()))
+                .collect::<Vec<_>>()
+        })
+        .filter(|model| opb.constraints.iter().all(|c| holds(c, model)))
+        .map(|model| opb.cost(&model).unwrap_or(0))
+        .min();
+
+    let model = solve(&opb, |_| {})?;
+    if let Some(model) = &model {
+        assert!(opb.constraints.iter().all(|c| holds(c, model)));
+    }
+    assert_eq!(model.map(|model| opb.cost(&model).unwrap_or(0)), best);
+    Ok(())
+}
@@ -0,0 +1,841 @@
+use super::Format;
+use crate::{dimacs::Cnf, ClauseId, Error, Literal, Sign, Variable, Watched};
+use fixedbitset::FixedBitSet;
+use std::collections::HashMap;
+
+/// A single line of a proof
+enum Step {
+    Add(Vec<Literal>),
+    Delete(Vec<Literal>),
+    /// An LRAT addition, with the IDs of the clauses it follows from.
+    /// Negative IDs start the hints for a RAT candidate.
+    AddWithHints(ClauseId, Vec<Literal>, Vec<isize>),
+    /// An LRAT deletion
+    DeleteIds(Vec<ClauseId>),
+}
+
+/// How many variables a proof may introduce beyond those of the problem,
+/// which keeps a malformed proof from exhausting memory
+const MAX_NEW_VARIABLES: usize = 1 << 24;
+
+/// Checks proofs of unsatisfiability against the problem they refute.
+///
+/// DRAT proofs are checked forwards, with every added clause having to
+/// follow by reverse unit propagation (or failing that, be a resolution
+/// asymmetric tautology on its first literal) from the clauses before it.
+/// LRAT proofs are checked using only the clauses named in their hints.
+pub struct Checker {
+    /// Every clause seen so far, with the original clauses first. Watched
+    /// literals are kept at the front of each clause.
+    clauses: Vec<Option<Vec<Literal>>>,
+    num_original: usize,
+    ids: HashMap<ClauseId, usize>,
+    lookup: HashMap<Vec<Literal>, Vec<usize>>,
+    watched: Watched,
+    values: Vec<Option<Sign>>,
+    reasons: Vec<Option<usize>>,
+    trail: Vec<Literal>,
+    propagated: usize,
+    /// The clauses each clause was derived from, if a core is wanted
+    dependencies: Option<Vec<Vec<usize>>>,
+    /// The clauses the empty clause was derived from, once it has been
+    refutation: Option<Vec<usize>>,
+}
+
+impl Checker {
+    pub fn new(cnf: &Cnf) -> Self {
+        let mut checker = Self {
+            clauses: Vec::with_capacity(cnf.clauses.len()),
+            num_original: cnf.clauses.len(),
+            ids: HashMap::new(),
+            lookup: HashMap::new(),
+            watched: Watched::new(0),
+            values: Vec::new(),
+            reasons: Vec::new(),
+            trail: Vec::new(),
+            propagated: 0,
+            dependencies: None,
+            refutation: None,
+        };
+        checker.grow(cnf.num_variables);
+        for clause in cnf.clauses.iter() {
+            let mut clause = clause.clone();
+            clause.sort();
+            clause.dedup();
+            checker.clauses.push(Some(clause));
+        }
+        checker
+    }
+
+    /// Records what every added clause was derived from, so that `core` can
+    /// be used once the proof has been checked
+    pub fn with_core(mut self) -> Self {
+        self.dependencies = Some(vec![Vec::new(); self.num_original]);
+        self
+    }
+
+    /// Checks that `proof` derives the empty clause from the problem
+    pub fn check(&mut self, proof: &[u8], format: Format) -> Result<(), Error> {
+        if let Some(empty) = self
+            .clauses
+            .iter()
+            .position(|clause| clause.as_ref().map(Vec::is_empty).unwrap_or(false))
+        {
+            self.refutation = Some(vec![empty]);
+            return Ok(());
+        }
+
+        let steps = match format {
+            Format::Drat => parse_text(proof, parse_drat_line),
+            Format::BinaryDrat => parse_binary(proof),
+            Format::Lrat => parse_text(proof, parse_lrat_line),
+        }
+        .map_err(Error::InvalidProof)?;
+
+        let limit = self.values.len() + MAX_NEW_VARIABLES;
+        for (number, step) in steps.iter().enumerate() {
+            let clause = match step {
+                Step::Add(clause) | Step::Delete(clause) | Step::AddWithHints(_, clause, _) => {
+                    clause
+                }
+                Step::DeleteIds(_) => continue,
+            };
+            if let Some(literal) = clause.iter().find(|literal| literal.var() >= limit) {
+                return Err(Error::InvalidProof(format!(
+                    "Proof step {}: variable {} is too far beyond the problem's {}",
+                    number + 1,
+                    literal.var() + 1,
+                    self.values.len()
+                )));
+            }
+        }
+
+        if format == Format::Lrat {
+            for idx in 0..self.num_original {
+                self.ids.insert(idx + 1, idx);
+            }
+        } else {
+            for idx in 0..self.num_original {
+                self.index(idx);
+                if self.refutation.is_none() {
+                    self.attach(idx);
+                }
+            }
+        }
+
+        for (number, step) in steps.into_iter().enumerate() {
+            if self.refutation.is_some() {
+                break;
+            }
+            match step {
+                Step::Add(clause) => self.add(clause),
+                Step::Delete(clause) => {
+                    self.delete(clause);
+                    Ok(())
+                }
+                Step::AddWithHints(id, clause, hints) => self.add_with_hints(id, clause, &hints),
+                Step::DeleteIds(ids) => {
+                    for id in ids {
+                        if let Some(idx) = self.ids.remove(&id) {
+                            self.clauses[idx] = None;
+                        }
+                    }
+                    Ok(())
+                }
+            }
+            .map_err(|e| Error::InvalidProof(format!("Proof step {}: {}", number + 1, e)))?;
+        }
+
+        match self.refutation {
+            Some(_) => Ok(()),
+            None => Err(Error::InvalidProof(
+                "The proof never derives the empty clause".to_owned(),
+            )),
+        }
+    }
+
+    /// The indices of the original clauses that the empty clause was derived
+    /// from, if the proof was checked with `with_core`
+    pub fn core(&self) -> Option<Vec<usize>> {
+        let (dependencies, refutation) = (self.dependencies.as_ref()?, self.refutation.as_ref()?);
+        let mut seen = FixedBitSet::with_capacity(self.clauses.len());
+        let mut pending = refutation.clone();
+        while let Some(idx) = pending.pop() {
+            if !seen.put(idx) {
+                pending.extend(dependencies[idx].iter().copied());
+            }
+        }
+        Some(
+            seen.ones()
+                .take_while(|idx| *idx < self.num_original)
+                .collect(),
+        )
+    }
+
+    fn grow(&mut self, num_variables: usize) {
+        if num_variables > self.values.len() {
+            self.values.resize(num_variables, None);
+            self.reasons.resize(num_variables, None);
+            self.watched.grow(num_variables);
+        }
+    }
+
+    fn ensure_variables(&mut self, clause: &[Literal]) {
+        if let Some(max) = clause.iter().map(|literal| literal.var()).max() {
+            self.grow(max + 1);
+        }
+    }
+
+    fn value(&self, literal: Literal) -> Option<bool> {
+        value(&self.values, literal)
+    }
+
+    fn assign(&mut self, literal: Literal, reason: Option<usize>) {
+        self.values[literal.var()] = Some(literal.sign());
+        self.reasons[literal.var()] = reason;
+        self.trail.push(literal);
+    }
+
+    /// Unassigns everything after the first `len` literals of the trail
+    fn backtrack(&mut self, len: usize) {
+        for literal in self.trail.drain(len..) {
+            self.values[literal.var()] = None;
+            self.reasons[literal.var()] = None;
+        }
+        self.propagated = std::cmp::min(self.propagated, len);
+    }
+
+    /// Adds a clause that has passed its check
+    fn push(&mut self, clause: Vec<Literal>, dependencies: Vec<usize>) -> usize {
+        self.clauses.push(Some(clause));
+        if let Some(all) = &mut self.dependencies {
+            all.push(dependencies);
+        }
+        self.clauses.len() - 1
+    }
+
+    fn index(&mut self, idx: usize) {
+        if let Some(clause) = &self.clauses[idx] {
+            let mut key = clause.clone();
+            key.sort();
+            key.dedup();
+            self.lookup.entry(key).or_default().push(idx);
+        }
+    }
+
+    /// Watches a clause, assigning its literal if it's unit and propagating
+    /// everything that follows at the top level
+    fn attach(&mut self, idx: usize) {
+        let values = &self.values;
+        let clause = match &mut self.clauses[idx] {
+            Some(clause) => clause,
+            None => return,
+        };
+        clause.sort_by_key(|literal| value(values, *literal) == Some(false));
+        if clause.len() >= 2 {
+            self.watched[clause[0]].push(idx);
+            self.watched[clause[1]].push(idx);
+        }
+
+        // Falsified literals were sorted to the back, so the clause is unit
+        // if its second literal is false
+        let first = clause.first().copied();
+        let unit = clause.len() == 1 || value(values, clause[1]) == Some(false);
+        let conflict = match first.map(|literal| (literal, self.value(literal))) {
+            None | Some((_, Some(false))) => Some(idx),
+            Some((literal, None)) if unit => {
+                self.assign(literal, Some(idx));
+                self.propagate()
+            }
+            Some(_) => None,
+        };
+        if let Some(conflict) = conflict {
+            let mut refutation = self.reasons_for(self.literals(conflict));
+            refutation.push(conflict);
+            self.refutation = Some(refutation);
+        }
+    }
+
+    /// Propagates every assigned literal, returning a falsified clause if
+    /// there is one
+    fn propagate(&mut self) -> Option<usize> {
+        while let Some(&literal) = self.trail.get(self.propagated) {
+            self.propagated += 1;
+            let falsified = !literal;
+            let mut watching = std::mem::take(&mut self.watched[falsified]);
+            let mut conflict = None;
+
+            let mut i = 0;
+            while i < watching.len() {
+                let idx = watching[i];
+                let values = &self.values;
+                let clause = match &mut self.clauses[idx] {
+                    Some(clause) => clause,
+                    None => {
+                        watching.swap_remove(i);
+                        continue;
+                    }
+                };
+                if clause[0] == falsified {
+                    clause.swap(0, 1);
+                }
+                if value(values, clause[0]) == Some(true) {
+                    i += 1;
+                    continue;
+                }
+
+                // Find a replacement for the falsified watch
+                if let Some(replacement) =
+                    (2..clause.len()).find(|k| value(values, clause[*k]) != Some(false))
+                {
+                    clause.swap(1, replacement);
+                    let watch = clause[1];
+                    self.watched[watch].push(idx);
+                    watching.swap_remove(i);
+                    continue;
+                }
+
+                let implied = clause[0];
+                if value(values, implied) == Some(false) {
+                    conflict = Some(idx);
+                    break;
+                }
+                self.assign(implied, Some(idx));
+                i += 1;
+            }
+
+            self.watched[falsified] = watching;
+            if conflict.is_some() {
+                return conflict;
+            }
+        }
+        None
+    }
+
+    fn literals(&self, idx: usize) -> Vec<Literal> {
+        self.clauses[idx].clone().unwrap_or_default()
+    }
+
+    /// The clauses that were propagated to assign the given literals, found
+    /// by walking back through the trail. Empty unless a core is wanted,
+    /// since nothing else needs them.
+    fn reasons_for(&self, literals: Vec<Literal>) -> Vec<usize> {
+        if self.dependencies.is_none() {
+            return Vec::new();
+        }
+        let mut seen = FixedBitSet::with_capacity(self.values.len());
+        for literal in literals {
+            seen.insert(literal.var());
+        }
+
+        let mut reasons = Vec::new();
+        for literal in self.trail.iter().rev() {
+            if !seen.contains(literal.var()) {
+                continue;
+            }
+            if let Some(reason) = self.reasons[literal.var()] {
+                reasons.push(reason);
+                for other in self.clauses[reason].iter().flatten() {
+                    seen.insert(other.var());
+                }
+            }
+        }
+        reasons
+    }
+
+    /// Checks whether falsifying `clause` leads to a conflict by unit
+    /// propagation, returning the clauses used if it does
+    fn rup(&mut self, clause: &[Literal]) -> Option<Vec<usize>> {
+        let mark = self.trail.len();
+        let mut satisfied = None;
+        for literal in clause {
+            match self.value(*literal) {
+                Some(true) => {
+                    satisfied = Some(*literal);
+                    break;
+                }
+                Some(false) => (),
+                None => self.assign(!*literal, None),
+            }
+        }
+
+        let result = match satisfied {
+            // Falsifying the clause contradicts whatever assigned the literal
+            Some(literal) => Some(self.reasons_for(vec![literal])),
+            None => self.propagate().map(|conflict| {
+                let mut reasons = self.reasons_for(self.literals(conflict));
+                reasons.push(conflict);
+                reasons
+            }),
+        };
+
+        self.backtrack(mark);
+        result
+    }
+
+    /// Checks that `clause` is a resolution asymmetric tautology on its first
+    /// literal: every resolvent with a clause containing the negated literal
+    /// must follow by reverse unit propagation
+    fn rat(&mut self, clause: &[Literal]) -> Option<Vec<usize>> {
+        let pivot = *clause.first()?;
+        let candidates = self.containing(!pivot);
+
+        let mut dependencies = Vec::new();
+        for candidate in candidates {
+            let mut resolvent = clause.to_vec();
+            resolvent.extend(
+                self.clauses[candidate]
+                    .iter()
+                    .flatten()
+                    .filter(|literal| **literal != !pivot),
+            );
+            dependencies.extend(self.rup(&resolvent)?);
+            dependencies.push(candidate);
+        }
+        Some(dependencies)
+    }
+
+    /// Every remaining clause containing `literal`
+    fn containing(&self, literal: Literal) -> Vec<usize> {
+        self.clauses
+            .iter()
+            .enumerate()
+            .filter(|(_, clause)| matches!(clause, Some(clause) if clause.contains(&literal)))
+            .map(|(idx, _)| idx)
+            .collect()
+    }
+
+    fn add(&mut self, clause: Vec<Literal>) -> Result<(), String> {
+        self.ensure_variables(&clause);
+        let dependencies = self
+            .rup(&clause)
+            .or_else(|| self.rat(&clause))
+            .ok_or_else(|| format!("Clause {} is not implied", display(&clause)))?;
+
+        let mut clause = clause;
+        clause.sort();
+        clause.dedup();
+        let idx = self.push(clause, dependencies);
+        self.index(idx);
+        self.attach(idx);
+        Ok(())
+    }
+
+    /// Removes a clause, unless it's a unit or the reason for a top level
+    /// assignment. Like `drat-trim`, those deletions are ignored, since
+    /// undoing the assignment would mean propagating everything again.
+    fn delete(&mut self, mut clause: Vec<Literal>) {
+        clause.sort();
+        clause.dedup();
+        let idx = match self
+            .lookup
+            .get(&clause)
+            .and_then(|matching| matching.last())
+        {
+            Some(idx) => *idx,
+            None => return,
+        };
+
+        let stored = self.clauses[idx]
+            .as_ref()
+            .expect("deleted clauses aren't indexed");
+        let is_reason = stored
+            .first()
+            .map(|literal| self.reasons[literal.var()] == Some(idx))
+            .unwrap_or(false);
+        if stored.len() > 1 && !is_reason {
+            if let Some(matching) = self.lookup.get_mut(&clause) {
+                matching.pop();
+            }
+            self.clauses[idx] = None;
+        }
+    }
+
+    fn add_with_hints(
+        &mut self,
+        id: ClauseId,
+        clause: Vec<Literal>,
+        hints: &[isize],
+    ) -> Result<(), String> {
+        if self.ids.contains_key(&id) {
+            return Err(format!("Clause ID {} is already in use", id));
+        }
+        self.ensure_variables(&clause);
+
+        let mark = self.trail.len();
+        let mut tautology = false;
+        for literal in clause.iter() {
+            match self.value(*literal) {
+                Some(true) => tautology = true,
+                Some(false) => (),
+                None => self.assign(!*literal, None),
+            }
+        }
+
+        // Hints for RAT candidates start at the first negative ID
+        let split = hints
+            .iter()
+            .position(|hint| *hint < 0)
+            .unwrap_or(hints.len());
+        let result = if tautology || self.run_hints(&hints[..split])? {
+            Ok(())
+        } else {
+            self.check_rat(&clause, &hints[split..])
+        };
+        self.backtrack(mark);
+        result?;
+
+        let dependencies = hints
+            .iter()
+            .filter_map(|hint| self.ids.get(&(hint.unsigned_abs())).copied())
+            .collect();
+        let empty = clause.is_empty();
+        let idx = self.push(clause, dependencies);
+        self.ids.insert(id, idx);
+        if empty {
+            self.refutation = Some(vec![idx]);
+        }
+        Ok(())
+    }
+
+    /// Propagates each hinted clause in turn, returning true if one of them
+    /// is falsified
+    fn run_hints(&mut self, hints: &[isize]) -> Result<bool, String> {
+        for hint in hints {
+            let clause = self
+                .ids
+                .get(&(*hint as ClauseId))
+                .and_then(|idx| self.clauses[*idx].as_ref())
+                .ok_or_else(|| format!("Hint {} is not a clause", hint))?;
+
+            let mut unassigned = None;
+            for literal in clause {
+                match self.value(*literal) {
+                    Some(true) => return Err(format!("Hint {} is satisfied", hint)),
+                    Some(false) => (),
+                    None if unassigned.is_none() || unassigned == Some(*literal) => {
+                        unassigned = Some(*literal)
+                    }
+                    None => return Err(format!("Hint {} is not unit", hint)),
+                }
+            }
+            match unassigned {
+                None => return Ok(true),
+                Some(literal) => self.assign(literal, None),
+            }
+        }
+        Ok(false)
+    }
+
+    /// Checks the RAT hints for `clause`, which must have one group for every
+    /// clause containing its negated first literal
+    fn check_rat(&mut self, clause: &[Literal], hints: &[isize]) -> Result<(), String> {
+        let pivot = *clause
+            .first()
+            .ok_or_else(|| "The empty clause is not implied".to_owned())?;
+
+        let mut groups = HashMap::new();
+        let mut rest = hints;
+        while let Some((candidate, after)) = rest.split_first() {
+            let len = after
+                .iter()
+                .position(|hint| *hint < 0)
+                .unwrap_or(after.len());
+            groups.insert(candidate.unsigned_abs(), &after[..len]);
+            rest = &after[len..];
+        }
+
+        let mut candidates = self
+            .ids
+            .iter()
+            .map(|(id, idx)| (*id, *idx))
+            .filter(
+                |(_, idx)| matches!(&self.clauses[*idx], Some(other) if other.contains(&!pivot)),
+            )
+            .collect::<Vec<_>>();
+        candidates.sort();
+
+        for (id, idx) in candidates {
+            let others = self.clauses[idx]
+                .iter()
+                .flatten()
+                .copied()
+                .filter(|literal| *literal != !pivot)
+                .collect::<Vec<_>>();
+            if others.iter().any(|literal| clause.contains(&!*literal)) {
+                continue;
+            }
+
+            let group = groups
+                .get(&id)
+                .ok_or_else(|| format!("No RAT hints for clause {}", id))?;
+            let mark = self.trail.len();
+            let mut conflict = false;
+            for literal in others {
+                match self.value(literal) {
+                    Some(true) => conflict = true,
+                    Some(false) => (),
+                    None => self.assign(!literal, None),
+                }
+            }
+            let conflict = conflict || self.run_hints(group)?;
+            self.backtrack(mark);
+            if !conflict {
+                return Err(format!(
+                    "RAT hints for clause {} don't lead to a conflict",
+                    id
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+fn value(values: &[Option<Sign>], literal: Literal) -> Option<bool> {
+    values[literal.var()].map(|sign| sign == literal.sign())
+}
+
+fn display(clause: &[Literal]) -> String {
+    let literals = clause
+        .iter()
+        .map(|literal| isize::from(*literal).to_string())
+        .collect::<Vec<_>>();
+    format!("[{}]", literals.join(" "))
+}
+
+fn parse_text(
+    proof: &[u8],
+    parse_line: impl Fn(&[&str]) -> Result<Step, String>,
+) -> Result<Vec<Step>, String> {
+    let proof = std::str::from_utf8(proof).map_err(|e| e.to_string())?;
+    proof
+        .lines()
+        .enumerate()
+        .map(|(number, line)| (number, line.split_whitespace().collect::<Vec<_>>()))
+        .filter(|(_, tokens)| !tokens.is_empty() && tokens[0] != "c")
+        .map(|(number, tokens)| {
+            parse_line(&tokens).map_err(|e| format!("Proof line {}: {}", number + 1, e))
+        })
+        .collect()
+}
+
+fn parse_drat_line(tokens: &[&str]) -> Result<Step, String> {
+    match tokens {
+        ["d", rest @ ..] => parse_literals(rest).map(|(clause, _)| Step::Delete(clause)),
+        _ => parse_literals(tokens).map(|(clause, _)| Step::Add(clause)),
+    }
+}
+
+fn parse_lrat_line(tokens: &[&str]) -> Result<Step, String> {
+    let (id, rest) = tokens
+        .split_first()
+        .ok_or_else(|| "Missing clause ID".to_owned())?;
+    let id = id
+        .parse()
+        .map_err(|e| format!("Invalid clause ID: {}", e))?;
+    match rest {
+        ["d", rest @ ..] => parse_literals(rest).map(|(ids, _)| {
+            Step::DeleteIds(
+                ids.into_iter()
+                    .map(|id| isize::from(id) as ClauseId)
+                    .collect(),
+            )
+        }),
+        _ => {
+            let (clause, rest) = parse_literals(rest)?;
+            let (hints, _) = parse_numbers(rest)?;
+            Ok(Step::AddWithHints(id, clause, hints))
+        }
+    }
+}
+
+/// Parses literals up to a terminating 0, returning what's left after it
+fn parse_literals<'a>(tokens: &'a [&'a str]) -> Result<(Vec<Literal>, &'a [&'a str]), String> {
+    let (numbers, rest) = parse_numbers(tokens)?;
+    Ok((numbers.into_iter().map(Literal::from).collect(), rest))
+}
+
+fn parse_numbers<'a>(tokens: &'a [&'a str]) -> Result<(Vec<isize>, &'a [&'a str]), String> {
+    let end = tokens
+        .iter()
+        .position(|token| *token == "0")
+        .ok_or_else(|| "Missing terminating 0".to_owned())?;
+    let numbers = tokens[..end]
+        .iter()
+        .map(|token| {
+            let number = token
+                .parse::<isize>()
+                .map_err(|e| format!("Invalid number: {}", e))?;
+            // Anything larger couldn't be turned into a literal
+            match number.unsigned_abs() < Variable::MAX >> 1 {
+                true => Ok(number),
+                false => Err(format!("Number out of range: {}", number)),
+            }
+        })
+        .collect::<Result<Vec<_>, String>>()?;
+    Ok((numbers, &tokens[end + 1..]))
+}
+
+/// Parses binary DRAT, in which each clause is an `a` or `d` byte followed by
+/// variable-length encoded literals and a terminating 0
+fn parse_binary(proof: &[u8]) -> Result<Vec<Step>, String> {
+    let mut steps = Vec::new();
+    let mut bytes = proof.iter().copied();
+    while let Some(marker) = bytes.next() {
+        let mut clause = Vec::new();
+        loop {
+            let (mut encoded, mut shift) = (0usize, 0);
+            loop {
+                let byte = bytes
+                    .next()
+                    .ok_or_else(|| "Binary proof ends mid-clause".to_owned())?;
+                let bits = (byte & 0x7f) as usize;
+                encoded |= bits
+                    .checked_shl(shift)
+                    .filter(|shifted| shifted >> shift == bits)
+                    .ok_or_else(|| "Binary literal is too large".to_owned())?;
+                shift += 7;
+                if byte & 0x80 == 0 {
+                    break;
+                }
+            }
+            if encoded == 0 {
+                break;
+            }
+            if encoded < 2 {
+                return Err(format!("Invalid binary literal {}", encoded));
+            }
+            let var: Variable = encoded / 2 - 1;
+            clause.push(Literal::new(var, encoded & 1 == 0));
+        }
+        steps.push(match marker {
+            b'a' => Step::Add(clause),
+            b'd' => Step::Delete(clause),
+            _ => return Err(format!("Invalid binary proof marker {}", marker)),
+        });
+    }
+    Ok(steps)
+}
+
+#[cfg(test)]
+fn square() -> Cnf {
+    let clauses: &[&[isize]] = &[&[1, 2], &[-1, 2], &[1, -2], &[-1, -2]];
+    Cnf {
+        num_variables: 2,
+        clauses: clauses
+            .iter()
+            .map(|clause| clause.iter().map(|x| Literal::from(*x)).collect())
+            .collect(),
+        xors: Vec::new(),
+    }
+}
+
+#[test]
+fn drat() -> Result<(), Error> {
+    // The first clause is a RAT addition on a variable the problem doesn't use
+    let mut checker = Checker::new(&square()).with_core();
+    checker.check(b"3 0\n2 0\n0\n", Format::Drat)?;
+    assert_eq!(checker.core(), Some(vec![0, 1, 2, 3]));
+
+    let mut checker = Checker::new(&square());
+    checker.check(&[b'a', 6, 0, b'a', 4, 0, b'a', 0], Format::BinaryDrat)?;
+    assert_eq!(checker.core(), None);
+
+    assert!(matches!(
+        Checker::new(&square()).check(b"", Format::Drat),
+        Err(Error::InvalidProof(_))
+    ));
+    assert!(Checker::new(&square())
+        .check(b"d 1 2 0\n2 0\n0\n", Format::Drat)
+        .is_err());
+    let mut satisfiable = square();
+    satisfiable.clauses.pop();
+    assert!(Checker::new(&satisfiable)
+        .check(b"2 0\n0\n", Format::Drat)
+        .is_err());
+    Ok(())
+}
+
+#[test]
+fn malformed_proofs() {
+    let invalid = |proof: &[u8], format| {
+        matches!(
+            Checker::new(&square()).check(proof, format),
+            Err(Error::InvalidProof(_))
+        )
+    };
+    // Too many continuation bytes for any literal
+    let mut long = vec![b'a'];
+    long.extend([0xff; 10].iter());
+    long.extend([0x01, 0].iter());
+    assert!(invalid(&long, Format::BinaryDrat));
+    // Literals that fit, but whose variables are far too large to track
+    assert!(invalid(
+        &[b'a', 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x01, 0],
+        Format::BinaryDrat
+    ));
+    assert!(invalid(b"1000000000000 0\n0\n", Format::Drat));
+    assert!(invalid(b"5 -1000000000000 0 0\n", Format::Lrat));
+    // Numbers that can't be turned into literals at all
+    assert!(invalid(b"-9223372036854775808 0\n0\n", Format::Drat));
+    assert!(invalid(b"9223372036854775807 0\n0\n", Format::Drat));
+    assert!(invalid(b"5 d -9223372036854775808 0\n", Format::Lrat));
+}
+
+#[test]
+fn lrat() -> Result<(), Error> {
+    let mut checker = Checker::new(&square()).with_core();
+    checker.check(
+        b"5 3 0 0\n6 2 0 1 2 0\n6 d 5 0\n7 0 6 3 4 0\n",
+        Format::Lrat,
+    )?;
+    assert_eq!(checker.core(), Some(vec![0, 1, 2, 3]));
+
+    // Every hint must be unit or falsified when it's reached
+    assert!(Checker::new(&square())
+        .check(b"5 2 0 3 1 0\n6 0 5 3 4 0\n", Format::Lrat)
+        .is_err());
+    // Without hints for the clauses containing 1, this isn't RAT on -1
+    assert!(Checker::new(&square())
+        .check(b"5 -1 3 0 0\n", Format::Lrat)
+        .is_err());
+    Ok(())
+}
+
+#[test]
+fn solver_proofs() -> Result<(), Error> {
+    use crate::{
+        dimacs,
+        proof::{Proof, SharedBuffer},
+        Solution, Solver,
+    };
+
+    let cnf = dimacs::parse_file("inputs/aim-100.cnf")?;
+    for format in [Format::Drat, Format::BinaryDrat, Format::Lrat].iter() {
+        let buffer = SharedBuffer::default();
+        let mut solver = Solver::new();
+        solver.set_proof(Proof::with_format(buffer.clone(), *format))?;
+        solver.add_cnf(&cnf)?;
+        assert!(matches!(solver.solve()?, Solution::Unsat));
+
+        let mut checker = Checker::new(&cnf).with_core();
+        checker.check(&buffer.bytes(), *format)?;
+        let core = checker.core().unwrap();
+        assert!(!core.is_empty() && core.len() < cnf.clauses.len());
+    }
+
+    // Later clauses can't refer to a clause that's missing from the proof
+    let buffer = SharedBuffer::default();
+    let mut solver = Solver::new();
+    solver.set_proof(Proof::with_format(buffer.clone(), Format::Lrat))?;
+    solver.add_cnf(&cnf)?;
+    assert!(matches!(solver.solve()?, Solution::Unsat));
+    let contents = buffer.contents();
+    let mut lines = contents.lines().collect::<Vec<_>>();
+    let hint = lines.last().unwrap().split_whitespace().nth(2).unwrap();
+    lines.retain(|line| !line.starts_with(&format!("{} ", hint)));
+    assert!(Checker::new(&cnf)
+        .check(lines.join("\n").as_bytes(), Format::Lrat)
+        .is_err());
+    Ok(())
+}
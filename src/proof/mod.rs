@@ -0,0 +1,222 @@
+use crate::{ClauseId, Error, Literal, Sign};
+use std::io::{BufWriter, Write};
+
+mod check;
+
+pub use check::Checker;
+
+/// The encodings a proof can be written in
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Format {
+    /// Plain text DRAT, one clause per line
+    Drat,
+    /// Binary DRAT, which takes up much less space than text
+    BinaryDrat,
+    /// Plain text LRAT, in which every clause has an ID and lists the clauses
+    /// that make it unit propagate to a conflict
+    Lrat,
+}
+
+impl std::str::FromStr for Format {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "drat" => Ok(Self::Drat),
+            "binary" => Ok(Self::BinaryDrat),
+            "lrat" => Ok(Self::Lrat),
+            _ => Err(Error::InvalidInput(format!("Unknown proof format: {}", s))),
+        }
+    }
+}
+
+/// A proof of unsatisfiability, written as the solver runs.
+///
+/// Clauses are written using the variable numbering of the input format, so
+/// the proof can be checked against the original problem file by external
+/// tools such as `drat-trim`. LRAT proofs also refer to the clauses of that
+/// file by their position, starting from 1.
+pub struct Proof {
+    out: BufWriter<Box<dyn Write>>,
+    format: Format,
+    last_id: ClauseId,
+}
+
+impl Proof {
+    /// A proof written as plain text DRAT
+    pub fn new(out: impl Write + 'static) -> Self {
+        Self::with_format(out, Format::Drat)
+    }
+
+    pub fn with_format(out: impl Write + 'static, format: Format) -> Self {
+        Self {
+            out: BufWriter::new(Box::new(out)),
+            format,
+            last_id: 0,
+        }
+    }
+
+    pub fn format(&self) -> Format {
+        self.format
+    }
+
+    /// Records the addition of a clause implied by those before it. `hints`
+    /// lists the clauses that become unit, in order, once the new clause is
+    /// falsified, ending with one that is falsified outright; they're only
+    /// written to LRAT proofs.
+    pub fn add(
+        &mut self,
+        id: ClauseId,
+        clause: impl IntoIterator<Item = Literal>,
+        hints: &[ClauseId],
+    ) -> Result<(), Error> {
+        if self.format == Format::Lrat {
+            self.last_id = id;
+            return self.write_lrat(id, clause, hints).map_err(Error::Io);
+        }
+        self.write_clause(false, clause)
+    }
+
+    /// Records that a clause is no longer needed
+    pub fn delete(
+        &mut self,
+        id: ClauseId,
+        clause: impl IntoIterator<Item = Literal>,
+    ) -> Result<(), Error> {
+        if self.format == Format::Lrat {
+            return writeln!(self.out, "{} d {} 0", self.last_id, id).map_err(Error::Io);
+        }
+        self.write_clause(true, clause)
+    }
+
+    pub fn flush(&mut self) -> Result<(), Error> {
+        self.out.flush().map_err(Error::Io)
+    }
+
+    fn write_clause(
+        &mut self,
+        deletion: bool,
+        clause: impl IntoIterator<Item = Literal>,
+    ) -> Result<(), Error> {
+        let (out, format) = (&mut self.out, self.format);
+        let write = || match format {
+            Format::Drat => {
+                if deletion {
+                    write!(out, "d ")?;
+                }
+                for literal in clause {
+                    write!(out, "{} ", isize::from(literal))?;
+                }
+                writeln!(out, "0")
+            }
+            Format::BinaryDrat => {
+                out.write_all(if deletion { b"d" } else { b"a" })?;
+                for literal in clause {
+                    write_binary_literal(out, literal)?;
+                }
+                out.write_all(&[0])
+            }
+            Format::Lrat => unreachable!("LRAT clauses are written with their IDs"),
+        };
+        write().map_err(Error::Io)
+    }
+
+    fn write_lrat(
+        &mut self,
+        id: ClauseId,
+        clause: impl IntoIterator<Item = Literal>,
+        hints: &[ClauseId],
+    ) -> std::io::Result<()> {
+        write!(self.out, "{} ", id)?;
+        for literal in clause {
+            write!(self.out, "{} ", isize::from(literal))?;
+        }
+        write!(self.out, "0 ")?;
+        for hint in hints {
+            write!(self.out, "{} ", hint)?;
+        }
+        writeln!(self.out, "0")
+    }
+}
+
+/// Writes a literal as `2 * var + negated` (with variables numbered from 1)
+/// in a variable-length encoding of 7 bits per byte, least significant first,
+/// with the top bit set on every byte but the last
+fn write_binary_literal(out: &mut impl Write, literal: Literal) -> std::io::Result<()> {
+    let mut encoded = 2 * (literal.var() + 1) + matches!(literal.sign(), Sign::Negative) as usize;
+    while encoded > 0x7f {
+        out.write_all(&[(encoded & 0x7f) as u8 | 0x80])?;
+        encoded >>= 7;
+    }
+    out.write_all(&[encoded as u8])
+}
+
+/// A buffer that can be handed to a `Proof` and read back afterwards
+#[cfg(test)]
+#[derive(Clone, Default)]
+pub(crate) struct SharedBuffer(std::rc::Rc<std::cell::RefCell<Vec<u8>>>);
+
+#[cfg(test)]
+impl SharedBuffer {
+    pub fn contents(&self) -> String {
+        String::from_utf8(self.bytes()).unwrap()
+    }
+
+    pub fn bytes(&self) -> Vec<u8> {
+        self.0.borrow().clone()
+    }
+}
+
+#[cfg(test)]
+impl Write for SharedBuffer {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.borrow_mut().write(buf)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+#[test]
+fn text() -> Result<(), Error> {
+    let buffer = SharedBuffer::default();
+    let mut proof = Proof::new(buffer.clone());
+    proof.add(4, vec![Literal::new(0, true), Literal::new(4, false)], &[1])?;
+    proof.delete(2, vec![Literal::new(2, true)])?;
+    proof.add(5, vec![], &[])?;
+    proof.flush()?;
+    assert_eq!(buffer.contents(), "1 -5 0\nd 3 0\n0\n");
+    Ok(())
+}
+
+#[test]
+fn binary() -> Result<(), Error> {
+    let buffer = SharedBuffer::default();
+    let mut proof = Proof::with_format(buffer.clone(), Format::BinaryDrat);
+    proof.add(4, vec![Literal::new(0, true), Literal::new(4, false)], &[1])?;
+    proof.delete(2, vec![Literal::new(99, false)])?;
+    proof.add(5, vec![], &[])?;
+    proof.flush()?;
+    assert_eq!(
+        buffer.bytes(),
+        vec![b'a', 2, 11, 0, b'd', 0xc9, 0x01, 0, b'a', 0]
+    );
+    Ok(())
+}
+
+#[test]
+fn lrat() -> Result<(), Error> {
+    let buffer = SharedBuffer::default();
+    let mut proof = Proof::with_format(buffer.clone(), Format::Lrat);
+    proof.add(
+        4,
+        vec![Literal::new(0, true), Literal::new(4, false)],
+        &[1, 3],
+    )?;
+    proof.delete(2, vec![Literal::new(2, true)])?;
+    proof.add(5, vec![], &[4, 2, 1])?;
+    proof.flush()?;
+    assert_eq!(buffer.contents(), "4 1 -5 0 1 3 0\n4 d 2 0\n5 0 4 2 1 0\n");
+    Ok(())
+}
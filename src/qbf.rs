@@ -0,0 +1,497 @@
+use crate::{
+    qdimacs::{Qdimacs, Quantifier},
+    Error, Literal, Sign, Solution, Solver, Variable,
+};
+
+/// The value of a quantified Boolean formula
+#[derive(Clone, Debug, PartialEq)]
+pub struct Answer {
+    pub value: bool,
+    /// A move for the outermost block that wins whatever the inner blocks
+    /// do: an assignment that makes the formula true if the block is
+    /// existential and the formula is true, or false if the block is
+    /// universal and the formula is false. There's no certificate when the
+    /// outermost block loses.
+    pub certificate: Option<Vec<(Variable, Sign)>>,
+}
+
+/// Decides a quantified Boolean formula by recursive abstraction refinement
+/// (RAReQS). The outermost block proposes a move from an abstraction of the
+/// formula, the rest of the formula is solved recursively to find a
+/// counter-move, and the abstraction is refined with the formula expanded by
+/// that counter-move until the move wins or the abstraction runs out of
+/// moves. Formulas with one alternation are solved by two incremental
+/// solvers, one proposing moves and one answering them.
+pub fn solve(qdimacs: &Qdimacs) -> Result<Answer, Error> {
+    let qbf = Qbf::from(qdimacs);
+    let mut num_variables = qdimacs.num_variables;
+    let quantifier = match qbf.prefix.first() {
+        Some((quantifier, _)) => *quantifier,
+        None => {
+            // Without any variables the matrix simplifies to a constant
+            let value = matches!(&qbf.matrix, Node::And(children) if children.is_empty());
+            return Ok(Answer {
+                value,
+                certificate: value.then(Vec::new),
+            });
+        }
+    };
+    let certificate = rareqs(&qbf, &mut num_variables)?;
+    Ok(Answer {
+        value: certificate.is_some() == (quantifier == Quantifier::Exists),
+        certificate: certificate.map(|moves| {
+            moves
+                .into_iter()
+                .map(|literal| (literal.var(), literal.sign()))
+                .collect()
+        }),
+    })
+}
+
+/// A formula in negation normal form
+#[derive(Clone, Debug)]
+enum Node {
+    Literal(Literal),
+    /// True if every child is, so true without any children
+    And(Vec<Node>),
+    /// True if any child is, so false without any children
+    Or(Vec<Node>),
+}
+
+impl Node {
+    fn constant(value: bool) -> Node {
+        match value {
+            true => Node::And(Vec::new()),
+            false => Node::Or(Vec::new()),
+        }
+    }
+
+    fn negate(&self) -> Node {
+        match self {
+            Node::Literal(literal) => Node::Literal(!*literal),
+            Node::And(children) => Node::Or(children.iter().map(Node::negate).collect()),
+            Node::Or(children) => Node::And(children.iter().map(Node::negate).collect()),
+        }
+    }
+
+    /// Replaces each literal by what `map` gives for it, simplifying away the
+    /// constants that come out of that
+    fn substitute(&self, map: &dyn Fn(Literal) -> Node) -> Node {
+        let (children, and) = match self {
+            Node::Literal(literal) => return map(*literal),
+            Node::And(children) => (children, true),
+            Node::Or(children) => (children, false),
+        };
+        let mut kept = Vec::new();
+        for child in children {
+            match child.substitute(map) {
+                // A constant that decides the node
+                Node::And(c) if c.is_empty() && !and => return Node::constant(true),
+                Node::Or(c) if c.is_empty() && and => return Node::constant(false),
+                // A constant that doesn't matter
+                Node::And(c) | Node::Or(c) if c.is_empty() => {}
+                child => kept.push(child),
+            }
+        }
+        match (kept.len(), and) {
+            (1, _) => kept.pop().unwrap(),
+            (_, true) => Node::And(kept),
+            (_, false) => Node::Or(kept),
+        }
+    }
+
+    /// Replaces the variables `values` assigns by their values
+    fn assign(&self, values: &[Option<bool>]) -> Node {
+        self.substitute(
+            &|literal| match values.get(literal.var()).copied().flatten() {
+                Some(value) => Node::constant(value == literal.sign().into()),
+                None => Node::Literal(literal),
+            },
+        )
+    }
+}
+
+/// A quantified Boolean formula whose prefix alternates, with no empty
+/// blocks, and which quantifies every variable of its matrix
+struct Qbf {
+    prefix: Vec<(Quantifier, Vec<Variable>)>,
+    matrix: Node,
+}
+
+impl From<&Qdimacs> for Qbf {
+    fn from(qdimacs: &Qdimacs) -> Self {
+        let mut quantified = vec![false; qdimacs.num_variables];
+        for var in qdimacs.prefix.iter().flat_map(|(_, variables)| variables) {
+            quantified[*var] = true;
+        }
+        let free = (0..qdimacs.num_variables).filter(|var| !quantified[*var]);
+        let blocks = std::iter::once((Quantifier::Exists, free.collect::<Vec<_>>()))
+            .chain(qdimacs.prefix.iter().cloned());
+
+        let mut prefix = Vec::<(Quantifier, Vec<Variable>)>::new();
+        for (quantifier, variables) in blocks.filter(|(_, variables)| !variables.is_empty()) {
+            match prefix.last_mut() {
+                Some((last, block)) if *last == quantifier => block.extend(variables),
+                _ => prefix.push((quantifier, variables)),
+            }
+        }
+        let clauses = qdimacs.clauses.iter().map(|clause| {
+            Node::Or(
+                clause
+                    .iter()
+                    .map(|literal| Node::Literal(*literal))
+                    .collect(),
+            )
+        });
+        Qbf {
+            prefix,
+            matrix: Node::And(clauses.collect()).substitute(&Node::Literal),
+        }
+    }
+}
+
+impl Qbf {
+    /// What the player of the outermost block wants the matrix to be
+    fn goal(&self) -> Node {
+        match self.prefix[0].0 {
+            Quantifier::Exists => self.matrix.clone(),
+            Quantifier::Forall => self.matrix.negate(),
+        }
+    }
+
+    /// The rest of the formula once the outermost block has played `moves`
+    fn after(&self, moves: &[Literal], num_variables: usize) -> Qbf {
+        Qbf {
+            prefix: self.prefix[1..].to_vec(),
+            matrix: self.matrix.assign(&values(moves, num_variables)),
+        }
+    }
+}
+
+/// Finds a move for the outermost block of `qbf` that wins whatever the inner
+/// blocks do, if there is one. Copies of variables are numbered from
+/// `num_variables` up.
+fn rareqs(qbf: &Qbf, num_variables: &mut usize) -> Result<Option<Vec<Literal>>, Error> {
+    match qbf.prefix.len() {
+        1 => {
+            let mut solver = Solver::new();
+            solver.add_variables(*num_variables);
+            assert(&qbf.goal(), &mut solver)?;
+            return moves(solver.solve()?, &qbf.prefix[0].1);
+        }
+        2 => return one_alternation(qbf, *num_variables),
+        _ => {}
+    }
+
+    // The abstraction starts out with the outermost block alone, which can
+    // make any move, and gains a copy of the rest of the formula for every
+    // counter-move found
+    let (quantifier, block) = qbf.prefix[0].clone();
+    let mut abstraction = Qbf {
+        prefix: vec![(quantifier, block.clone())],
+        matrix: Node::constant(quantifier == Quantifier::Exists),
+    };
+    loop {
+        let candidate = match rareqs(&abstraction, num_variables)? {
+            Some(candidate) => candidate,
+            None => return Ok(None),
+        };
+        let candidate = restrict(candidate, &block);
+        let counter = match rareqs(&qbf.after(&candidate, *num_variables), num_variables)? {
+            Some(counter) => counter,
+            None => return Ok(Some(candidate)),
+        };
+
+        // Copy the blocks inside the counter-move's with fresh variables
+        let mut renamed = vec![None; *num_variables];
+        for (idx, (quantifier, variables)) in qbf.prefix[2..].iter().enumerate() {
+            let copies = variables.iter().map(|var| {
+                renamed[*var] = Some(*num_variables);
+                *num_variables += 1;
+                *num_variables - 1
+            });
+            let copies = copies.collect::<Vec<_>>();
+            match abstraction.prefix.get_mut(idx) {
+                Some((_, block)) => block.extend(copies),
+                None => abstraction.prefix.push((*quantifier, copies)),
+            }
+        }
+        let countered = values(&counter, renamed.len());
+        let expansion = qbf.matrix.substitute(&|literal| {
+            let var = literal.var();
+            match (
+                countered.get(var).copied().flatten(),
+                renamed.get(var).copied().flatten(),
+            ) {
+                (Some(value), _) => Node::constant(value == literal.sign().into()),
+                (None, Some(copy)) => Node::Literal(Literal::new(copy, literal.sign())),
+                (None, None) => Node::Literal(literal),
+            }
+        });
+        abstraction.matrix = match (abstraction.matrix.clone(), quantifier) {
+            (Node::And(mut children), Quantifier::Exists) => {
+                children.push(expansion);
+                Node::And(children)
+            }
+            (Node::Or(mut children), Quantifier::Forall) => {
+                children.push(expansion);
+                Node::Or(children)
+            }
+            (matrix, Quantifier::Exists) => Node::And(vec![matrix, expansion]),
+            (matrix, Quantifier::Forall) => Node::Or(vec![matrix, expansion]),
+        };
+    }
+}
+
+/// Solves a formula with two blocks, keeping one solver for the moves of the
+/// outer block that beat every counter-move found so far, and another for
+/// the counter-moves, which is given each move as assumptions
+fn one_alternation(qbf: &Qbf, num_variables: usize) -> Result<Option<Vec<Literal>>, Error> {
+    let (block, inner) = (&qbf.prefix[0].1, &qbf.prefix[1].1);
+    let goal = qbf.goal();
+    let mut candidates = Solver::new();
+    candidates.add_variables(num_variables);
+    let mut counters = Solver::new();
+    counters.add_variables(num_variables);
+    assert(&goal.negate(), &mut counters)?;
+    loop {
+        let candidate = match moves(candidates.solve()?, block)? {
+            Some(candidate) => candidate,
+            None => return Ok(None),
+        };
+        let counter = match moves(counters.solve_with_assumptions(&candidate)?, inner)? {
+            Some(counter) => counter,
+            None => return Ok(Some(candidate)),
+        };
+        assert(
+            &goal.assign(&values(&counter, num_variables)),
+            &mut candidates,
+        )?;
+    }
+}
+
+/// Adds clauses that can be satisfied exactly when `node` can
+fn assert(node: &Node, solver: &mut Solver) -> Result<(), Error> {
+    match node {
+        Node::And(children) => children.iter().try_for_each(|child| assert(child, solver)),
+        node => {
+            let literal = encode(node, solver)?;
+            solver.add_clause(&[literal])
+        }
+    }
+}
+
+/// A literal that implies `node`, defined by clauses added to `solver` with
+/// the Plaisted-Greenbaum encoding
+fn encode(node: &Node, solver: &mut Solver) -> Result<Literal, Error> {
+    let children = match node {
+        Node::Literal(literal) => return Ok(*literal),
+        Node::And(children) | Node::Or(children) => children
+            .iter()
+            .map(|child| encode(child, solver))
+            .collect::<Result<Vec<_>, _>>()?,
+    };
+    let literal = Literal::new(solver.new_variable(), true);
+    match node {
+        Node::And(_) => {
+            for child in children {
+                solver.add_clause(&[!literal, child])?;
+            }
+        }
+        _ => {
+            let mut clause = children;
+            clause.push(!literal);
+            solver.add_clause(&clause)?;
+        }
+    }
+    Ok(literal)
+}
+
+/// The values a solution gives the variables of `block`, if it has any
+fn moves(
+    solution: Solution<Vec<(Variable, Sign)>>,
+    block: &[Variable],
+) -> Result<Option<Vec<Literal>>, Error> {
+    match solution {
+        Solution::Sat(model) => Ok(Some(
+            block
+                .iter()
+                .map(|var| Literal::new(*var, model[*var].1))
+                .collect(),
+        )),
+        Solution::Unsat => Ok(None),
+        Solution::Unknown => Err(Error::ResourceLimit),
+    }
+}
+
+/// The literals of `moves` over variables of `block`
+fn restrict(mut moves: Vec<Literal>, block: &[Variable]) -> Vec<Literal> {
+    moves.retain(|literal| block.contains(&literal.var()));
+    moves
+}
+
+/// The value of each variable that `moves` assigns, indexed by variable
+fn values(moves: &[Literal], num_variables: usize) -> Vec<Option<bool>> {
+    let mut values = vec![None; num_variables];
+    for literal in moves {
+        values[literal.var()] = Some(literal.sign().into());
+    }
+    values
+}
+
+#[cfg(test)]
+fn qdimacs(prefix: &[(Quantifier, &[isize])], clauses: &[&[isize]]) -> Qdimacs {
+    let literals = |x: &[isize]| x.iter().map(|x| Literal::from(*x)).collect::<Vec<_>>();
+    Qdimacs {
+        num_variables: clauses
+            .iter()
+            .flat_map(|clause| clause.iter())
+            .chain(prefix.iter().flat_map(|(_, block)| block.iter()))
+            .map(|x| x.unsigned_abs())
+            .max()
+            .unwrap_or(0),
+        prefix: prefix
+            .iter()
+            .map(|(quantifier, block)| {
+                let block = block.iter().map(|x| x.unsigned_abs() - 1).collect();
+                (*quantifier, block)
+            })
+            .collect(),
+        clauses: clauses.iter().map(|clause| literals(clause)).collect(),
+    }
+}
+
+#[test]
+fn known_formulas() -> Result<(), Error> {
+    use Quantifier::*;
+
+    // ∀x ∃y. x = y
+    let equal: &[&[isize]] = &[&[1, -2], &[-1, 2]];
+    let answer = solve(&qdimacs(&[(Forall, &[1]), (Exists, &[2])], equal))?;
+    assert_eq!(
+        answer,
+        Answer {
+            value: true,
+            certificate: None
+        }
+    );
+
+    // ∃y ∀x. x = y has no y that works, and whichever x the universal
+    // player picks, the other value beats it
+    let answer = solve(&qdimacs(&[(Exists, &[2]), (Forall, &[1])], equal))?;
+    assert_eq!(
+        answer,
+        Answer {
+            value: false,
+            certificate: None
+        }
+    );
+
+    // ∃a ∀x ∃y. (a ∨ x) ∧ (x = y): a has to be true
+    let answer = solve(&qdimacs(
+        &[(Exists, &[1]), (Forall, &[2]), (Exists, &[3])],
+        &[&[1, 2], &[2, -3], &[-2, 3]],
+    ))?;
+    assert_eq!(
+        answer,
+        Answer {
+            value: true,
+            certificate: Some(vec![(0, Sign::Positive)])
+        }
+    );
+
+    // ∀x ∃y. y ∧ ¬y is false, and any x is a counterexample
+    let answer = solve(&qdimacs(&[(Forall, &[1]), (Exists, &[2])], &[&[2], &[-2]]))?;
+    assert!(!answer.value);
+    assert_eq!(answer.certificate.map(|moves| moves.len()), Some(1));
+
+    // An empty matrix is true, and an empty clause false
+    assert!(solve(&qdimacs(&[], &[]))?.value);
+    assert!(!solve(&qdimacs(&[], &[&[]]))?.value);
+    Ok(())
+}
+
+#[cfg(test)]
+impl quickcheck::Arbitrary for Qdimacs {
+    /// A formula small enough to decide by brute force
+    fn arbitrary<G: quickcheck::Gen>(g: &mut G) -> Self {
+        use rand::Rng;
+
+        let num_variables = g.gen_range(1, 9);
+        let mut prefix = Vec::<(Quantifier, Vec<Variable>)>::new();
+        for var in 0..num_variables {
+            let quantifier = match g.gen_range(0, 3) {
+                0 => continue,
+                1 => Quantifier::Exists,
+                _ => Quantifier::Forall,
+            };
+            match prefix.last_mut() {
+                Some((last, block)) if *last == quantifier && g.gen() => block.push(var),
+                _ => prefix.push((quantifier, vec![var])),
+            }
+        }
+        let clauses = (0..g.gen_range(1, 4 * num_variables))
+            .map(|_| {
+                (0..g.gen_range(1, 4))
+                    .map(|_| Literal::new(g.gen_range(0, num_variables), g.gen::<bool>()))
+                    .collect()
+            })
+            .collect();
+        Qdimacs {
+            num_variables,
+            prefix,
+            clauses,
+        }
+    }
+}
+
+#[cfg(test)]
+#[quickcheck_macros::quickcheck]
+fn matches_brute_force(formula: Qdimacs) -> Result<(), Error> {
+    /// Whether the formula is true once `values` gives the outer blocks
+    fn value(qdimacs: &Qdimacs, prefix: &[(Quantifier, Variable)], values: &mut [bool]) -> bool {
+        let (quantifier, var) = match prefix.first() {
+            Some(first) => *first,
+            None => {
+                return qdimacs.clauses.iter().all(|clause| {
+                    clause.iter().any(|literal| {
+                        let sign: bool = literal.sign().into();
+                        values[literal.var()] == sign
+                    })
+                })
+            }
+        };
+        let mut outcomes = [false, true].iter().map(|x| {
+            values[var] = *x;
+            value(qdimacs, &prefix[1..], values)
+        });
+        match quantifier {
+            Quantifier::Exists => outcomes.any(|x| x),
+            Quantifier::Forall => outcomes.all(|x| x),
+        }
+    }
+
+    // Free variables are existential and outermost
+    let mut order = (0..formula.num_variables)
+        .filter(|var| !formula.prefix.iter().any(|(_, block)| block.contains(var)))
+        .map(|var| (Quantifier::Exists, var))
+        .collect::<Vec<_>>();
+    for (quantifier, block) in formula.prefix.iter() {
+        order.extend(block.iter().map(|var| (*quantifier, *var)));
+    }
+    let expected = value(&formula, &order, &mut vec![false; formula.num_variables]);
+
+    let answer = solve(&formula)?;
+    assert_eq!(answer.value, expected);
+    if let Some(certificate) = answer.certificate {
+        // The rest of the formula has the certificate's value after it
+        let mut values = vec![false; formula.num_variables];
+        for (var, sign) in certificate.iter() {
+            values[*var] = (*sign).into();
+        }
+        let rest = &order[certificate.len()..];
+        assert_eq!(value(&formula, rest, &mut values), expected);
+        assert_eq!(order[0].0 == Quantifier::Exists, expected);
+    }
+    Ok(())
+}
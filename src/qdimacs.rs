@@ -0,0 +1,268 @@
+use crate::{
+    dimacs::{open, parse_header, read_lines, tokens, ParseError, ParseErrorKind},
+    Error, Literal, Variable,
+};
+use fixedbitset::FixedBitSet;
+use std::{
+    fmt::{self, Formatter},
+    io::Read,
+    path::Path,
+};
+
+/// A quantified Boolean formula in prenex conjunctive normal form, as read
+/// from a QDIMACS file
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Qdimacs {
+    pub num_variables: usize,
+    /// The quantifier blocks, outermost first. Variables that no block
+    /// quantifies are existential, outside every block.
+    pub prefix: Vec<(Quantifier, Vec<Variable>)>,
+    pub clauses: Vec<Vec<Literal>>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Quantifier {
+    Exists,
+    Forall,
+}
+
+/// The ways in which a QDIMACS file can fail to be valid, beyond those it
+/// shares with DIMACS
+#[derive(Clone, Debug, PartialEq)]
+pub enum ErrorKind {
+    /// A quantifier line comes after a clause, or quantifies a variable that
+    /// was already quantified
+    InvalidQuantifier,
+    /// A quantifier line lists a negative literal rather than a variable
+    InvalidVariable,
+}
+
+impl ErrorKind {
+    pub(crate) fn describe(&self, f: &mut Formatter, found: &str) -> fmt::Result {
+        match self {
+            ErrorKind::InvalidQuantifier => write!(
+                f,
+                "expected quantifiers before every clause, each quantifying a new variable, \
+                 found {}",
+                found
+            ),
+            ErrorKind::InvalidVariable => write!(
+                f,
+                "expected a variable (a positive integer) to quantify, found {}",
+                found
+            ),
+        }
+    }
+}
+
+pub fn parse_file(path: impl AsRef<Path>) -> Result<Qdimacs, Error> {
+    read(open(path.as_ref())?)
+}
+
+/// Reads a problem in QDIMACS format, decompressing it as in `dimacs::read`
+pub fn read(reader: impl Read) -> Result<Qdimacs, Error> {
+    let mut parser = Parser::default();
+    read_lines(reader, |line| parser.parse_line(line))?;
+    parser.finish()
+}
+
+/// Parses a problem in QDIMACS format: a DIMACS header, then lines that each
+/// start with `e` or `a` and list the variables they quantify until a
+/// terminating 0, then clauses laid out as in `dimacs::parse`. Adjacent
+/// blocks with the same quantifier are merged.
+pub fn parse(lines: impl IntoIterator<Item = impl AsRef<str>>) -> Result<Qdimacs, Error> {
+    let mut parser = Parser::default();
+    for line in lines {
+        parser.parse_line(line.as_ref())?;
+    }
+    parser.finish()
+}
+
+#[derive(Default)]
+struct Parser {
+    qdimacs: Qdimacs,
+    /// The number of the last line parsed
+    line: usize,
+    header: bool,
+    /// The variables quantified so far
+    quantified: FixedBitSet,
+    clause: Option<Vec<Literal>>,
+}
+
+impl Parser {
+    fn parse_line(&mut self, line: &str) -> Result<(), Error> {
+        self.line += 1;
+        let tokens = tokens(line);
+        let quantifier = match tokens.first() {
+            None => return Ok(()),
+            Some((_, token)) if token.starts_with('c') => return Ok(()),
+            _ if !self.header => {
+                self.qdimacs.num_variables = parse_header(self.line, line)?.num_variables;
+                self.header = true;
+                return Ok(());
+            }
+            Some((_, "e")) => Some(Quantifier::Exists),
+            Some((_, "a")) => Some(Quantifier::Forall),
+            _ => None,
+        };
+
+        let number = self.line;
+        // Larger variables can't be told apart from their literals' signs
+        let num_variables = self.qdimacs.num_variables.min((Variable::MAX >> 1) - 1);
+        let error = |kind, (column, token): (usize, &str)| ParseError {
+            kind,
+            line: number,
+            column,
+            token: token.to_owned(),
+        };
+        let literal = |token: (usize, &str)| -> Result<isize, ParseError> {
+            let literal = token
+                .1
+                .parse::<isize>()
+                .map_err(|_| error(ParseErrorKind::InvalidLiteral, token))?;
+            match literal.unsigned_abs() > num_variables {
+                true => Err(error(
+                    ParseErrorKind::VariableOutOfRange(num_variables),
+                    token,
+                )),
+                false => Ok(literal),
+            }
+        };
+
+        if let Some(quantifier) = quantifier {
+            if self.clause.is_some() || !self.qdimacs.clauses.is_empty() {
+                return Err(error(
+                    ParseErrorKind::Qdimacs(ErrorKind::InvalidQuantifier),
+                    tokens[0],
+                )
+                .into());
+            }
+            let mut block = Vec::new();
+            for token in tokens.into_iter().skip(1) {
+                let var = match literal(token)? {
+                    0 => break,
+                    literal if literal < 0 => {
+                        let kind = ParseErrorKind::Qdimacs(ErrorKind::InvalidVariable);
+                        return Err(error(kind, token).into());
+                    }
+                    literal => literal as usize - 1,
+                };
+                // The header's count can't be trusted, so room is only made
+                // for the variables quantified
+                self.quantified.grow(var + 1);
+                if self.quantified.put(var) {
+                    return Err(error(
+                        ParseErrorKind::Qdimacs(ErrorKind::InvalidQuantifier),
+                        token,
+                    )
+                    .into());
+                }
+                block.push(var);
+            }
+            match self.qdimacs.prefix.last_mut() {
+                _ if block.is_empty() => {}
+                Some((last, variables)) if *last == quantifier => variables.extend(block),
+                _ => self.qdimacs.prefix.push((quantifier, block)),
+            }
+            return Ok(());
+        }
+
+        for token in tokens {
+            let literals = self.clause.get_or_insert_with(Vec::new);
+            match literal(token)? {
+                0 => self.qdimacs.clauses.push(self.clause.take().unwrap()),
+                literal => literals.push(Literal::from(literal)),
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns the problem, including a last clause without a terminating 0
+    fn finish(mut self) -> Result<Qdimacs, Error> {
+        if !self.header {
+            return Err(ParseError::at_end(ParseErrorKind::MissingHeader, self.line + 1).into());
+        }
+        self.qdimacs.clauses.extend(self.clause.take());
+        Ok(self.qdimacs)
+    }
+}
+
+#[test]
+fn parse_qdimacs() -> Result<(), Error> {
+    let literal = |x: isize| Literal::from(x);
+    let qdimacs = parse([
+        "c one alternation",
+        "p cnf 4 2",
+        "e 1 0",
+        "e 2 0",
+        "a 3",
+        "e 0",
+        "1 -3 0 2",
+        "4 -1 0",
+    ])?;
+    assert_eq!(
+        qdimacs,
+        Qdimacs {
+            num_variables: 4,
+            prefix: vec![
+                (Quantifier::Exists, vec![0, 1]),
+                (Quantifier::Forall, vec![2]),
+            ],
+            clauses: vec![
+                vec![literal(1), literal(-3)],
+                vec![literal(2), literal(4), literal(-1)],
+            ],
+        }
+    );
+    Ok(())
+}
+
+#[test]
+fn qdimacs_errors() {
+    use crate::dimacs::parse_error;
+    let error = |lines: &[&str]| parse_error(parse(lines));
+    assert_eq!(
+        error(&["e 1 0"]),
+        (ParseErrorKind::MissingHeader, 1, 1, "e".to_owned())
+    );
+    assert_eq!(
+        error(&["p cnf 2 1", "1 2 0", "a 1 0"]),
+        (
+            ParseErrorKind::Qdimacs(ErrorKind::InvalidQuantifier),
+            3,
+            1,
+            "a".to_owned()
+        )
+    );
+    assert_eq!(
+        error(&["p cnf 2 1", "e 1 0", "a 2 1 0"]),
+        (
+            ParseErrorKind::Qdimacs(ErrorKind::InvalidQuantifier),
+            3,
+            5,
+            "1".to_owned()
+        )
+    );
+    assert_eq!(
+        error(&["p cnf 2 1", "a 3 0"]),
+        (ParseErrorKind::VariableOutOfRange(2), 2, 3, "3".to_owned())
+    );
+    assert_eq!(
+        error(&["p cnf 18446744073709551615 1", "-9223372036854775808 0"]),
+        (
+            ParseErrorKind::VariableOutOfRange((Variable::MAX >> 1) - 1),
+            2,
+            1,
+            "-9223372036854775808".to_owned()
+        )
+    );
+    assert_eq!(
+        error(&["p cnf 2 1", "e -1 0"]),
+        (
+            ParseErrorKind::Qdimacs(ErrorKind::InvalidVariable),
+            2,
+            3,
+            "-1".to_owned()
+        )
+    );
+}
@@ -0,0 +1,130 @@
+use crate::{Error, Literal, Sign, Solution, Solver, Variable};
+
+/// An iterator over the distinct solutions of a formula, created by
+/// `Solver::solutions`
+pub struct Solutions<'a> {
+    solver: &'a mut Solver,
+    projection: Option<Vec<Variable>>,
+    blocking: Option<Vec<Literal>>,
+    started: bool,
+    done: bool,
+}
+
+impl<'a> Solutions<'a> {
+    pub(crate) fn new(solver: &'a mut Solver, projection: Option<Vec<Variable>>) -> Self {
+        Self {
+            solver,
+            projection,
+            blocking: None,
+            started: false,
+            done: false,
+        }
+    }
+
+    fn next_solution(&mut self) -> Result<Option<Vec<(Variable, Sign)>>, Error> {
+        // Forbid the previous solution and carry on from where it was found
+        if let Some(blocking) = self.blocking.take() {
+            if !self.solver.block(blocking)? {
+                return Ok(None);
+            }
+        } else if !self.started {
+            self.started = true;
+            if !self.solver.start(&[])? {
+                return Ok(None);
+            }
+        }
+
+        match self.solver.search(Default::default())? {
+            Solution::Unsat => Ok(None),
+            Solution::Unknown => Err(Error::ResourceLimit),
+            Solution::Sat(model) => {
+                let solution = match &self.projection {
+                    None => model,
+                    Some(projection) => projection
+                        .iter()
+                        .map(|var| (*var, self.solver.value(*var).unwrap()))
+                        .collect(),
+                };
+                self.blocking = Some(
+                    solution
+                        .iter()
+                        .map(|(var, sign)| !Literal::new(*var, *sign))
+                        .collect(),
+                );
+                Ok(Some(solution))
+            }
+        }
+    }
+}
+
+impl<'a> Iterator for Solutions<'a> {
+    type Item = Result<Vec<(Variable, Sign)>, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        let solution = self.next_solution().transpose();
+        self.done = !matches!(solution, Some(Ok(_)));
+        solution
+    }
+}
+
+impl<'a> Drop for Solutions<'a> {
+    fn drop(&mut self) {
+        let _ = self.solver.pop();
+    }
+}
+
+#[test]
+fn all_solutions() -> Result<(), Error> {
+    let (a, b) = (Literal::new(0, true), Literal::new(1, true));
+    let mut solver = Solver::new();
+    solver.add_variables(3);
+    solver.add_clause(&[a, b])?;
+
+    let solutions = solver.solutions(None).collect::<Result<Vec<_>, _>>()?;
+    assert_eq!(solutions.len(), 6);
+    assert!(solutions.iter().all(|solution| solution.len() == 3));
+    assert!(solutions
+        .iter()
+        .all(|solution| !solution.contains(&(0, Sign::Negative))
+            || !solution.contains(&(1, Sign::Negative))));
+
+    let mut projected = solver
+        .solutions(Some(&[1, 0]))
+        .collect::<Result<Vec<_>, _>>()?;
+    projected.sort_by_key(|solution| {
+        solution
+            .iter()
+            .map(|(_, sign)| *sign == Sign::Positive)
+            .collect::<Vec<_>>()
+    });
+    assert_eq!(
+        projected,
+        vec![
+            vec![(0, Sign::Negative), (1, Sign::Positive)],
+            vec![(0, Sign::Positive), (1, Sign::Negative)],
+            vec![(0, Sign::Positive), (1, Sign::Positive)],
+        ]
+    );
+
+    assert_eq!(solver.solutions(Some(&[])).count(), 1);
+
+    // Enumerating doesn't constrain the formula afterwards
+    assert_eq!(solver.num_groups(), 0);
+    solver.add_clause(&[!a])?;
+    assert_eq!(solver.solutions(Some(&[0, 1])).count(), 1);
+    assert!(matches!(solver.solve()?, Solution::Sat(_)));
+    Ok(())
+}
+
+#[test]
+fn no_solutions() -> Result<(), Error> {
+    let a = Literal::new(0, true);
+    let mut solver = Solver::new();
+    solver.add_clause(&[a])?;
+    solver.add_clause(&[!a])?;
+    assert_eq!(solver.solutions(None).count(), 0);
+    Ok(())
+}
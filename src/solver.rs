@@ -1,20 +1,23 @@
 use crate::{
+    dimacs::{self, Cnf},
     formula::{clause, Formula},
-    Assignment, Assignments, ClauseIdx, Conflict, Counters, DecisionLevel, History, Literal, Luby,
-    Sign, Variable, Watched,
+    gauss::Propagation,
+    opb::{Normalized, Opb},
+    proof::{Format, Proof},
+    Assignment, Assignments, Budget, ClauseId, ClauseIdx, Conflict, Counters, DecisionLevel, Error,
+    Evaluate, Gauss, History, Interrupt, Literal, Luby, Sign, Solutions, Statistics, Variable,
+    Watched,
 };
+use fixedbitset::FixedBitSet;
 use rand::{
     distributions::{Bernoulli, Distribution},
     rngs::ThreadRng,
 };
-use std::{
-    fs::File,
-    io::{BufRead, BufReader},
-    path::Path,
-};
+use std::{collections::BTreeMap, ops::Range, path::Path, time::Instant};
 
 const UNIT_RUN: usize = 100;
 const RANDOM_VAR_FREQ: f64 = 0.02;
+const INTERRUPT_CHECK_FREQ: usize = 1 << 12;
 
 pub struct Solver {
     decision_level: usize,
@@ -26,16 +29,44 @@ pub struct Solver {
     watched: Watched,
     conflict: Conflict,
     pending_update: Vec<ClauseIdx>,
+    gauss: Gauss,
+    /// Whether any pseudo-Boolean constraint has been added
+    has_constraints: bool,
+    /// The clauses explaining what the XOR constraints found above decision
+    /// level 0, along with the level each was added at
+    reasons: Vec<(DecisionLevel, ClauseIdx)>,
     luby: Luby,
     num_conflicts: usize,
     next_restart: usize,
     rng: ThreadRng,
     random_branch: Bernoulli,
+    unsat: bool,
+    assumptions: Vec<Literal>,
+    failed: Vec<Literal>,
+    groups: Vec<Variable>,
+    activation: FixedBitSet,
+    statistics: Statistics,
+    interrupt: Interrupt,
+    proof: Option<Proof>,
+    next_id: ClauseId,
+    unit_ids: Vec<Option<ClauseId>>,
+    resolved: Vec<ClauseIdx>,
+    refutation: Option<Refutation>,
+    originals: Option<Vec<Vec<Literal>>>,
+    /// The pseudo-Boolean constraints to check models against, kept along
+    /// with `originals`
+    original_constraints: Option<Vec<Normalized>>,
+    num_added: usize,
+    origins: Option<Vec<Origin>>,
+    empty_id: Option<ClauseId>,
+    final_dependencies: Option<Vec<ClauseId>>,
 }
 
 pub enum Solution<T: IntoIterator<Item = (Variable, Sign)>> {
     Sat(T),
     Unsat,
+    /// The search stopped before finding an answer
+    Unknown,
 }
 
 #[must_use]
@@ -44,110 +75,815 @@ pub enum Status {
     Ok,
     Conflict(ConflictType),
     Unsat,
+    Interrupted,
 }
 
 #[must_use]
-#[derive(Debug)]
+#[derive(Clone, Copy, Debug)]
 pub enum ConflictType {
     Clause(ClauseIdx),
     Literal(Literal),
 }
 
+/// What showed the formula to be unsatisfiable, kept until the empty clause
+/// can be added to the proof
+#[derive(Clone, Copy, Debug)]
+enum Refutation {
+    /// An empty clause was added
+    Empty(ClauseId),
+    /// A conflict was reached at decision level 0
+    Conflict(ConflictType),
+}
+
+/// Where a clause came from, for finding unsatisfiable cores
+#[derive(Clone, Debug)]
+enum Origin {
+    /// The clause was the nth given to `add_clause`
+    Original(usize),
+    /// The clause was derived from the clauses with these IDs
+    Derived(Vec<ClauseId>),
+}
+
+impl Default for Solver {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl Solver {
+    /// Creates a solver with no variables and no clauses
+    pub fn new() -> Self {
+        let mut luby = Luby::new();
+
+        Self {
+            formula: Formula::new(0),
+            counters: Counters::new(0),
+            assignments: Assignments::new(0),
+            history: History::new(0),
+            watched: Watched::new(0),
+            conflict: Conflict::new(0),
+            pending_update: Vec::new(),
+            gauss: Gauss::default(),
+            has_constraints: false,
+            reasons: Vec::new(),
+            decision_level: 0,
+            num_variables: 0,
+            next_restart: luby.next() * UNIT_RUN,
+            num_conflicts: 0,
+            luby,
+            rng: rand::thread_rng(),
+            random_branch: Bernoulli::new(RANDOM_VAR_FREQ).unwrap(),
+            unsat: false,
+            assumptions: Vec::new(),
+            failed: Vec::new(),
+            groups: Vec::new(),
+            activation: FixedBitSet::with_capacity(0),
+            statistics: Statistics::default(),
+            interrupt: Interrupt::default(),
+            proof: None,
+            next_id: 1,
+            unit_ids: Vec::new(),
+            resolved: Vec::new(),
+            refutation: None,
+            originals: None,
+            original_constraints: None,
+            num_added: 0,
+            origins: None,
+            empty_id: None,
+            final_dependencies: None,
+        }
+    }
+
     pub fn parse_and_solve_file(
         path: impl AsRef<Path>,
-    ) -> Result<Solution<impl IntoIterator<Item = (Variable, Sign)>>, String> {
-        let lines = File::open(path)
-            .map(|f| BufReader::new(f).lines().filter_map(Result::ok))
-            .map_err(|e| format!("{}", e))?;
-
-        Self::parse_and_solve(lines)
+    ) -> Result<Solution<impl IntoIterator<Item = (Variable, Sign)>>, Error> {
+        let file = dimacs::open(path.as_ref())?;
+        let mut solver = Self::new();
+        dimacs::load(file, &mut solver, false)?;
+        solver.solve_numbered()
     }
 
     pub fn parse_and_solve(
         lines: impl IntoIterator<Item = impl AsRef<str>>,
-    ) -> Result<Solution<impl IntoIterator<Item = (Variable, Sign)>>, String> {
-        let mut lines = lines
-            .into_iter()
-            .skip_while(|l| l.as_ref().starts_with('c'));
-        let problem_line = lines.next().ok_or_else(|| "No problem line".to_owned())?;
-        let problem = problem_line.as_ref().split_whitespace().collect::<Vec<_>>();
-
-        let clauses = lines.map(|l| {
-            l.as_ref()
-                .split_whitespace()
-                .filter(|x| *x != "0")
-                .map(|x| x.parse::<isize>().map(Literal::from))
-                .collect::<Result<Vec<_>, _>>()
-                .map_err(|e| format!("Invalid clause line: {}", e))
-        });
-
-        let (num_variables, num_clauses) = match problem.as_slice() {
-            ["p", "cnf", vars, clauses] => (
-                vars.parse().map_err(|e| format!("{}", e))?,
-                clauses.parse().map_err(|e| format!("{}", e))?,
-            ),
-            _ => Err("Invalid problem line".to_owned())?,
-        };
-
-        let mut parsed_clauses = Vec::with_capacity(num_clauses);
-        for clause in clauses {
-            parsed_clauses.push(clause?);
-        }
+    ) -> Result<Solution<impl IntoIterator<Item = (Variable, Sign)>>, Error> {
+        let mut solver = Self::new();
+        dimacs::parse_into(lines, &mut solver, false)?;
+        solver.solve_numbered()
+    }
 
-        Self::solve_clauses(parsed_clauses, num_variables).map(|solution| match solution {
+    /// Solves, numbering the variables of a solution from 1 as in the input
+    /// format
+    fn solve_numbered(
+        &mut self,
+    ) -> Result<Solution<impl IntoIterator<Item = (Variable, Sign)>>, Error> {
+        self.solve().map(|solution| match solution {
             Solution::Unsat => Solution::Unsat,
+            Solution::Unknown => Solution::Unknown,
             Solution::Sat(assignments) => {
                 Solution::Sat(assignments.into_iter().map(|(var, sign)| (var + 1, sign)))
             }
         })
     }
 
+    #[cfg(test)]
     fn solve_clauses(
         clauses: Vec<Vec<Literal>>,
         num_variables: usize,
-    ) -> Result<Solution<impl IntoIterator<Item = (Variable, Sign)>>, String> {
-        let mut luby = Luby::new();
+    ) -> Result<Solution<impl IntoIterator<Item = (Variable, Sign)>>, Error> {
+        let mut solver = Self::new();
+        solver.add_variables(num_variables);
 
-        let mut solver = Self {
-            formula: Formula::new(clauses.len()),
-            counters: Counters::new(num_variables),
-            assignments: Assignments::new(num_variables),
-            history: History::new(num_variables),
-            watched: Watched::new(num_variables),
-            conflict: Conflict::new(num_variables),
-            pending_update: Vec::with_capacity(clauses.len()),
-            decision_level: 0,
-            num_variables,
-            next_restart: luby.next() * UNIT_RUN,
-            num_conflicts: 0,
-            luby,
-            rng: rand::thread_rng(),
-            random_branch: Bernoulli::new(RANDOM_VAR_FREQ).unwrap(),
+        for clause in clauses {
+            solver.add_clause(&clause)?;
+        }
+
+        solver.solve()
+    }
+
+    /// Adds the variables and clauses of a parsed problem
+    pub fn add_cnf(&mut self, cnf: &Cnf) -> Result<(), Error> {
+        if cnf.num_variables > self.num_variables {
+            self.add_variables(cnf.num_variables - self.num_variables);
+        }
+        for clause in cnf.clauses.iter() {
+            self.add_clause(clause)?;
+        }
+        for xor in cnf.xors.iter() {
+            self.add_xor(xor)?;
+        }
+        Ok(())
+    }
+
+    /// Adds the variables and constraints of a parsed pseudo-Boolean problem,
+    /// leaving out its objective
+    pub fn add_opb(&mut self, opb: &Opb) -> Result<(), Error> {
+        if opb.num_variables > self.num_variables {
+            self.add_variables(opb.num_variables - self.num_variables);
+        }
+        for constraint in opb.constraints.iter() {
+            for (terms, degree) in constraint.normalize()? {
+                self.add_constraint(&terms, degree)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// The number of variables known to the solver
+    pub fn num_variables(&self) -> usize {
+        self.num_variables
+    }
+
+    /// Adds `count` fresh variables, returning the range of their indices
+    pub fn add_variables(&mut self, count: usize) -> Range<Variable> {
+        let start = self.num_variables;
+        self.num_variables += count;
+        self.counters.grow(count);
+        self.assignments.grow(self.num_variables);
+        self.watched.grow(self.num_variables);
+        self.conflict.grow(self.num_variables);
+        self.activation.grow(self.num_variables);
+        self.unit_ids.resize(2 * self.num_variables, None);
+        start..self.num_variables
+    }
+
+    fn ensure_variables(&mut self, literals: &[Literal]) {
+        if let Some(max) = literals.iter().map(|literal| literal.var()).max() {
+            if max >= self.num_variables {
+                self.add_variables(max + 1 - self.num_variables);
+            }
+        }
+    }
+
+    /// Adds a fresh variable, returning its index
+    pub fn new_variable(&mut self) -> Variable {
+        self.add_variables(1).start
+    }
+
+    /// Adds a clause to the formula, creating any variables it mentions that
+    /// don't exist yet. Clauses may be added between calls to `solve`, and
+    /// belong to the innermost clause group if one has been pushed.
+    pub fn add_clause(&mut self, clause: &[Literal]) -> Result<(), Error> {
+        self.ensure_variables(clause);
+        let id = self.fresh_id();
+        self.record(id, Origin::Original(self.num_added));
+        self.num_added += 1;
+
+        // Duplicate literals would be watched twice, and tautologies are
+        // always satisfied
+        let mut literals = clause.to_vec();
+        literals.sort();
+        literals.dedup();
+        if literals.windows(2).any(|pair| pair[0] == !pair[1]) {
+            return Ok(());
+        }
+
+        if self.unsat {
+            return Ok(());
+        }
+        self.backtrack(0);
+
+        // Clauses in a group only apply while its activation variable is
+        // assumed true
+        if let Some(group) = self.groups.last() {
+            let deactivated = Literal::new(*group, Sign::Negative);
+            self.prove_activated(id, deactivated, &literals)?;
+            literals.push(deactivated);
+        }
+        if let Some(originals) = &mut self.originals {
+            originals.push(literals.clone());
+        }
+
+        match self.learn_clause(id, literals.into_iter())? {
+            Status::Ok | Status::Interrupted => (),
+            Status::Unsat => self.refute(Refutation::Empty(id)),
+            Status::Conflict(conflict) => self.refute(Refutation::Conflict(conflict)),
+        }
+        Ok(())
+    }
+
+    /// Adds a pseudo-Boolean constraint: the coefficients of the true
+    /// literals in `terms` have to add up to at least `degree`. Unlike
+    /// encoding it into clauses, the constraint is kept whole and propagated
+    /// by keeping track of its slack. Groups apply as they do to clauses.
+    ///
+    /// Constraints can't be written to a proof, so none can be added once a
+    /// proof has been set. Nor can they be added while unsat cores are
+    /// tracked, as a core without them would be satisfiable.
+    pub fn add_constraint(&mut self, terms: &[(u64, Literal)], degree: u64) -> Result<(), Error> {
+        if self.proof.is_some() {
+            return Err(Error::InvalidInput(
+                "Pseudo-Boolean constraints can't be written to a proof".to_owned(),
+            ));
+        }
+        if self.origins.is_some() {
+            return Err(Error::InvalidInput(
+                "Pseudo-Boolean constraints can't be part of an unsat core".to_owned(),
+            ));
+        }
+        let literals = terms
+            .iter()
+            .map(|(_, literal)| *literal)
+            .collect::<Vec<_>>();
+        self.ensure_variables(&literals);
+        let overflow = || Error::InvalidInput("Coefficients are too large".to_owned());
+
+        // Each literal should appear once, and a literal and its negation
+        // together always contribute the smaller of their coefficients
+        let mut merged = BTreeMap::new();
+        for (coefficient, literal) in terms.iter().filter(|(c, _)| *c > 0) {
+            let sum = merged.entry(*literal).or_insert(0u64);
+            *sum = sum.checked_add(*coefficient).ok_or_else(overflow)?;
+        }
+        let mut degree = degree;
+        for literal in literals
+            .iter()
+            .filter(|literal| literal.sign() == Sign::Positive)
+        {
+            if let (Some(a), Some(b)) = (merged.get(literal), merged.get(&!*literal)) {
+                let both = *a.min(b);
+                degree = degree.saturating_sub(both);
+                for literal in [*literal, !*literal].iter() {
+                    if merged[literal] == both {
+                        merged.remove(literal);
+                    } else if let Some(coefficient) = merged.get_mut(literal) {
+                        *coefficient -= both;
+                    }
+                }
+            }
+        }
+        if degree == 0 || self.unsat {
+            return Ok(());
+        }
+        self.has_constraints = true;
+
+        // No literal can contribute more than the degree
+        let mut terms = merged
+            .into_iter()
+            .map(|(literal, coefficient)| (coefficient.min(degree), literal))
+            .collect::<Vec<_>>();
+        let total = terms
+            .iter()
+            .try_fold(0u64, |total, (coefficient, _)| {
+                total.checked_add(*coefficient)
+            })
+            .ok_or_else(overflow)?;
+        if let Some(group) = self.groups.last() {
+            terms.push((degree, Literal::new(*group, Sign::Negative)));
+        }
+        if let Some(constraints) = &mut self.original_constraints {
+            constraints.push((terms.clone(), degree));
+        }
+        self.backtrack(0);
+
+        let id = self.fresh_id();
+        let status = if total < degree {
+            // Nothing satisfies the constraint, which only rules out its group
+            // if it has one
+            let deactivated = self
+                .groups
+                .last()
+                .map(|group| Literal::new(*group, Sign::Negative));
+            self.learn_clause(id, deactivated.into_iter())?
+        } else if terms.iter().all(|(coefficient, _)| *coefficient == degree) {
+            // Any one true literal is enough, so it's just a clause
+            self.learn_clause(id, terms.into_iter().map(|(_, literal)| literal))?
+        } else {
+            let (idx, status) = self.formula.add_pb(
+                id,
+                terms,
+                degree,
+                &mut self.watched,
+                &mut self.counters,
+                &self.assignments,
+            )?;
+            match status {
+                clause::Status::Ok => Status::Ok,
+                clause::Status::Conflict => Status::Conflict(ConflictType::Clause(idx)),
+                clause::Status::Implied(literal) => {
+                    let status = self.assign_implied(literal, idx);
+                    self.requeue(idx);
+                    status
+                }
+            }
         };
+        match status {
+            Status::Ok | Status::Interrupted => (),
+            Status::Unsat => self.refute(Refutation::Empty(id)),
+            Status::Conflict(conflict) => self.refute(Refutation::Conflict(conflict)),
+        }
+        Ok(())
+    }
 
-        // Add clauses to formula
-        for clause in clauses {
-            match solver.learn_clause(clause.into_iter())? {
-                Status::Ok => (),
-                _ => return Ok(Solution::Unsat),
+    /// Adds an XOR constraint, which holds when an odd number of `literals`
+    /// are true. Rather than being encoded into clauses, XOR constraints are
+    /// kept together in a matrix, and Gauss-Jordan elimination on it finds
+    /// what they imply between them.
+    ///
+    /// As with pseudo-Boolean constraints, XOR constraints can't be written
+    /// to a proof. Nor can they be added while unsat cores are tracked, as a
+    /// core without them would be satisfiable, or belong to a clause group.
+    pub fn add_xor(&mut self, literals: &[Literal]) -> Result<(), Error> {
+        if self.proof.is_some() {
+            return Err(Error::InvalidInput(
+                "XOR constraints can't be written to a proof".to_owned(),
+            ));
+        }
+        if self.origins.is_some() {
+            return Err(Error::InvalidInput(
+                "XOR constraints can't be part of an unsat core".to_owned(),
+            ));
+        }
+        if !self.groups.is_empty() {
+            return Err(Error::InvalidInput(
+                "XOR constraints can't be added to a clause group".to_owned(),
+            ));
+        }
+        self.ensure_variables(literals);
+
+        // Each negative literal flips the parity the variables need
+        let negative = literals
+            .iter()
+            .filter(|literal| literal.sign() == Sign::Negative)
+            .count();
+        let variables = literals.iter().map(|literal| literal.var()).collect();
+        self.gauss.add(variables, negative.is_multiple_of(2));
+        Ok(())
+    }
+
+    /// Writes a proof to `proof` as the solver runs. Only clauses that are
+    /// derived by the solver are written, so the proof should be checked
+    /// against the clauses given to `add_clause`.
+    ///
+    /// LRAT proofs number clauses in the order they were given to
+    /// `add_clause`, so every clause should be added before the first call to
+    /// `solve` or `push` for the numbering to match the problem file.
+    ///
+    /// XOR and pseudo-Boolean constraints can't be written to a proof, so a
+    /// proof can't be set once any have been added.
+    pub fn set_proof(&mut self, proof: Proof) -> Result<(), Error> {
+        if !self.gauss.is_empty() || self.has_constraints {
+            return Err(Error::InvalidInput(
+                "A proof can't follow XOR or pseudo-Boolean constraints".to_owned(),
+            ));
+        }
+        self.proof = Some(proof);
+        Ok(())
+    }
+
+    /// Checks every satisfying assignment against the clauses and
+    /// constraints it's meant to satisfy before returning it, which means
+    /// keeping a copy of them. Only those added after verification is turned
+    /// on are checked.
+    pub fn set_verify(&mut self, verify: bool) {
+        if !verify {
+            self.originals = None;
+            self.original_constraints = None;
+        } else if self.originals.is_none() {
+            self.originals = Some(Vec::new());
+            self.original_constraints = Some(Vec::new());
+        }
+    }
+
+    /// Makes sure the current assignment satisfies every clause and
+    /// assumption, so that a bug in the search can't produce a wrong answer
+    fn verify_model(&self) -> Result<(), Error> {
+        let falsified = |clause: &[Literal]| {
+            !clause
+                .iter()
+                .any(|literal| literal.evaluate(&self.assignments) == Some(true))
+        };
+        let (originals, constraints) = match (&self.originals, &self.original_constraints) {
+            (Some(originals), Some(constraints)) => (originals, constraints),
+            _ => return Ok(()),
+        };
+
+        let activation = &self.activation;
+        if let Some(clause) = originals.iter().find(|clause| falsified(clause)) {
+            let literals = clause
+                .iter()
+                .filter(|literal| !activation.contains(literal.var()))
+                .map(|literal| isize::from(*literal).to_string())
+                .collect::<Vec<_>>();
+            return Err(Error::Internal(format!(
+                "model falsifies clause [{}]",
+                literals.join(" ")
+            )));
+        }
+        if let Some((terms, degree)) = constraints.iter().find(|(terms, degree)| {
+            let sum = terms
+                .iter()
+                .filter(|(_, literal)| literal.evaluate(&self.assignments) == Some(true))
+                .fold(0u64, |sum, (coefficient, _)| {
+                    sum.saturating_add(*coefficient)
+                });
+            sum < *degree
+        }) {
+            let terms = terms
+                .iter()
+                .filter(|(_, literal)| !activation.contains(literal.var()))
+                .map(|(coefficient, literal)| format!("{}*{}", coefficient, isize::from(*literal)))
+                .collect::<Vec<_>>();
+            return Err(Error::Internal(format!(
+                "model falsifies pseudo-Boolean constraint [{}] >= {}",
+                terms.join(" "),
+                degree
+            )));
+        }
+        if let Some(variables) = self.gauss.falsified(&self.assignments) {
+            let variables = variables
+                .iter()
+                .map(|var| (var + 1).to_string())
+                .collect::<Vec<_>>();
+            return Err(Error::Internal(format!(
+                "model falsifies XOR constraint over [{}]",
+                variables.join(" ")
+            )));
+        }
+        if let Some(assumption) = self
+            .assumptions
+            .iter()
+            .find(|assumption| falsified(&[**assumption]))
+        {
+            return Err(Error::Internal(format!(
+                "model falsifies assumption {}",
+                isize::from(*assumption)
+            )));
+        }
+        Ok(())
+    }
+
+    /// Keeps track of which clauses each learned clause was derived from, so
+    /// that `unsat_core` can report the clauses responsible for an `Unsat`
+    /// result. Only clauses added after tracking is turned on can be part of
+    /// a core.
+    pub fn set_track_core(&mut self, track: bool) {
+        if !track {
+            self.origins = None;
+        } else if self.origins.is_none() {
+            self.origins = Some(Vec::new());
+        }
+    }
+
+    /// The clauses responsible for the last `Unsat` result, given by their
+    /// position in the order they were passed to `add_clause`. Available
+    /// once `set_track_core` has been turned on, unless XOR or
+    /// pseudo-Boolean constraints were added before it was.
+    pub fn unsat_core(&self) -> Option<Vec<usize>> {
+        let origins = self
+            .origins
+            .as_ref()
+            .filter(|_| self.gauss.is_empty() && !self.has_constraints)?;
+        let mut pending = match self.empty_id {
+            Some(id) => vec![id],
+            None => self.final_dependencies.clone()?,
+        };
+
+        let mut seen = FixedBitSet::with_capacity(origins.len());
+        let mut core = Vec::new();
+        while let Some(id) = pending.pop() {
+            if seen.put(id) {
+                continue;
+            }
+            match origins.get(id) {
+                Some(Origin::Original(idx)) => core.push(*idx),
+                Some(Origin::Derived(from)) => pending.extend(from.iter().copied()),
+                None => (),
             }
         }
+        core.sort_unstable();
+        Some(core)
+    }
 
-        solver.solve()
+    fn record(&mut self, id: ClauseId, origin: Origin) {
+        if let Some(origins) = &mut self.origins {
+            if origins.len() <= id {
+                origins.resize(id + 1, Origin::Derived(Vec::new()));
+            }
+            origins[id] = origin;
+        }
+    }
+
+    /// Whether each new clause needs to know what it was derived from
+    fn needs_hints(&self) -> bool {
+        self.origins.is_some() || self.proving_lrat()
+    }
+
+    fn fresh_id(&mut self) -> ClauseId {
+        self.next_id += 1;
+        self.next_id - 1
+    }
+
+    /// Whether the proof needs hints for each clause it's given
+    fn proving_lrat(&self) -> bool {
+        matches!(&self.proof, Some(proof) if proof.format() == Format::Lrat)
+    }
+
+    fn prove(
+        &mut self,
+        id: ClauseId,
+        clause: impl IntoIterator<Item = Literal>,
+        hints: &[ClauseId],
+    ) -> Result<(), Error> {
+        match &mut self.proof {
+            Some(proof) => proof.add(id, clause, hints),
+            None => Ok(()),
+        }
+    }
+
+    /// Records a clause guarded by a group's activation variable. Putting the
+    /// negated activation literal first makes it a valid RAT addition, since
+    /// the activation literal itself never appears in a clause.
+    fn prove_activated(
+        &mut self,
+        id: ClauseId,
+        deactivated: Literal,
+        clause: &[Literal],
+    ) -> Result<(), Error> {
+        self.prove(
+            id,
+            std::iter::once(deactivated).chain(clause.iter().copied()),
+            &[],
+        )
+    }
+
+    /// Marks the formula as unsatisfiable. The proof isn't finished until the
+    /// next search, since original clauses added in the meantime must be
+    /// numbered before anything derived from them.
+    fn refute(&mut self, refutation: Refutation) {
+        self.unsat = true;
+        self.refutation = Some(refutation);
+    }
+
+    /// Finishes the proof with the empty clause, if that hasn't been done yet
+    fn prove_unsat(&mut self) -> Result<(), Error> {
+        let refutation = match self.refutation.take() {
+            Some(refutation) => refutation,
+            None => return Ok(()),
+        };
+
+        let hints = if self.needs_hints() {
+            match refutation {
+                Refutation::Empty(id) => vec![id],
+                Refutation::Conflict(ConflictType::Literal(literal)) => {
+                    vec![self.unit_id(literal)?, self.unit_id(!literal)?]
+                }
+                Refutation::Conflict(ConflictType::Clause(clause)) => {
+                    let falsified: Vec<Literal> = self.formula[clause]
+                        .reason(None, &self.assignments)
+                        .collect();
+                    let mut hints = falsified
+                        .into_iter()
+                        .map(|literal| self.unit_id(!literal))
+                        .collect::<Result<Vec<_>, _>>()?;
+                    hints.push(self.formula.id(clause));
+                    hints
+                }
+            }
+        } else {
+            Vec::new()
+        };
+
+        let id = self.fresh_id();
+        self.prove(id, std::iter::empty(), &hints)?;
+        self.record(id, Origin::Derived(hints));
+        self.empty_id = Some(id);
+        match &mut self.proof {
+            Some(proof) => proof.flush(),
+            None => Ok(()),
+        }
     }
 
-    fn solve(mut self) -> Result<Solution<impl IntoIterator<Item = (Variable, Sign)>>, String> {
+    /// The ID of a unit clause asserting `literal`, which must be true at
+    /// decision level 0. Literals implied at level 0 are only given one when
+    /// it's needed, by adding their antecedent's other literals as units
+    /// first.
+    fn unit_id(&mut self, literal: Literal) -> Result<ClauseId, Error> {
+        let mut pending = vec![literal];
+        while let Some(&literal) = pending.last() {
+            if self.unit_ids[literal.code()].is_some() {
+                pending.pop();
+                continue;
+            }
+
+            let antecedent = self
+                .assignments
+                .get(literal.var())
+                .filter(|assignment| {
+                    assignment.decision_level() == 0 && assignment.sign() == literal.sign()
+                })
+                .and_then(Assignment::antecedent)
+                .ok_or_else(|| {
+                    Error::Internal(format!("No unit clause can be derived for {}", literal))
+                })?;
+            let others: Vec<Literal> = self.formula[antecedent]
+                .reason(Some(literal), &self.assignments)
+                .map(|other| !other)
+                .collect();
+
+            let missing: Vec<Literal> = others
+                .iter()
+                .copied()
+                .filter(|other| self.unit_ids[other.code()].is_none())
+                .collect();
+            if !missing.is_empty() {
+                pending.extend(missing);
+                continue;
+            }
+
+            let mut hints: Vec<ClauseId> = others
+                .iter()
+                .filter_map(|other| self.unit_ids[other.code()])
+                .collect();
+            hints.push(self.formula.id(antecedent));
+            let id = self.fresh_id();
+            if self.proving_lrat() {
+                self.prove(id, std::iter::once(literal), &hints)?;
+            }
+            self.record(id, Origin::Derived(hints));
+            self.unit_ids[literal.code()] = Some(id);
+            pending.pop();
+        }
+        Ok(self.unit_ids[literal.code()].expect("unit was derived"))
+    }
+
+    /// Searches for an assignment satisfying every clause added so far.
+    /// Learned clauses and variable activity are kept between calls.
+    pub fn solve(&mut self) -> Result<Solution<Vec<(Variable, Sign)>>, Error> {
+        self.solve_with_assumptions(&[])
+    }
+
+    /// Searches for an assignment satisfying every clause added so far in
+    /// which each of the assumed literals is true. Assumptions only apply to
+    /// this call; if they can't all hold, `failed_assumptions` reports a
+    /// subset of them that is responsible.
+    pub fn solve_with_assumptions(
+        &mut self,
+        assumptions: &[Literal],
+    ) -> Result<Solution<Vec<(Variable, Sign)>>, Error> {
+        self.solve_with_budget(assumptions, Budget::unlimited())
+    }
+
+    /// Like `solve_with_assumptions`, but returns `Unknown` once any limit in
+    /// the budget is reached. Everything learned is kept, so a later call can
+    /// pick up where this one left off.
+    pub fn solve_with_budget(
+        &mut self,
+        assumptions: &[Literal],
+        budget: Budget,
+    ) -> Result<Solution<Vec<(Variable, Sign)>>, Error> {
+        if !self.start(assumptions)? {
+            return Ok(Solution::Unsat);
+        }
+        let solution = self.search(budget)?;
+        if let Some(proof) = &mut self.proof {
+            proof.flush()?;
+        }
+        Ok(solution)
+    }
+
+    /// Iterates over every satisfying assignment, or every distinct
+    /// assignment of `projection` if one is given. Each solution found is
+    /// forbidden before searching for the next, within a clause group that is
+    /// popped once the iterator is dropped.
+    pub fn solutions(&mut self, projection: Option<&[Variable]>) -> Solutions<'_> {
+        let projection = projection.map(|projection| {
+            let mut projection = projection.to_vec();
+            projection.sort();
+            projection.dedup();
+            if let Some(max) = projection.last() {
+                if *max >= self.num_variables {
+                    self.add_variables(max + 1 - self.num_variables);
+                }
+            }
+            projection
+        });
+        self.push();
+        Solutions::new(self, projection)
+    }
+
+    /// Resets the search to decision level 0 under new assumptions, returning
+    /// false if the formula is already known to be unsatisfiable
+    pub(crate) fn start(&mut self, assumptions: &[Literal]) -> Result<bool, Error> {
+        self.failed.clear();
+        self.final_dependencies = None;
+        if self.unsat {
+            self.prove_unsat()?;
+            return Ok(false);
+        }
+        self.backtrack(0);
+        if self.gauss.needs_rebuild() && !self.gauss.rebuild(&self.assignments) {
+            let id = self.fresh_id();
+            self.refute(Refutation::Empty(id));
+            self.prove_unsat()?;
+            return Ok(false);
+        }
+
+        self.ensure_variables(assumptions);
+        self.assumptions.clear();
+        self.assumptions.extend(
+            self.groups
+                .iter()
+                .map(|group| Literal::new(*group, Sign::Positive)),
+        );
+        self.assumptions.extend_from_slice(assumptions);
+        Ok(true)
+    }
+
+    /// Searches onward from the current state until a solution is found or
+    /// the budget runs out
+    pub(crate) fn search(
+        &mut self,
+        budget: Budget,
+    ) -> Result<Solution<Vec<(Variable, Sign)>>, Error> {
+        let (start, started) = (self.statistics, Instant::now());
+
         loop {
+            if self.interrupt.take()
+                || budget.exhausted(self.statistics - start, || started.elapsed())
+            {
+                return Ok(Solution::Unknown);
+            }
+
             match self.propogate_all() {
                 Status::Ok => {
+                    // Assumptions are decided before anything else
+                    match self.next_assumption() {
+                        Some(Ok(assumption)) => {
+                            self.new_decision_level();
+                            assert!(matches!(self.assign_decided(assumption), Status::Ok));
+                            continue;
+                        }
+                        Some(Err(failed)) => {
+                            let (core, antecedents) = self.analyze_final(failed);
+                            if self.origins.is_some() {
+                                self.final_dependencies =
+                                    Some(self.dependencies(failed, &antecedents)?);
+                            }
+                            self.failed = core;
+                            let activation = &self.activation;
+                            self.failed
+                                .retain(|literal| !activation.contains(literal.var()));
+                            return Ok(Solution::Unsat);
+                        }
+                        None => (),
+                    }
+
                     if self.all_variables_assigned() {
-                        return Ok(Solution::Sat(self.assignments.assignments()));
+                        self.verify_model()?;
+                        return Ok(Solution::Sat(self.model()));
                     }
                     self.branch()?;
                 }
-                Status::Unsat => return Ok(Solution::Unsat),
+                Status::Unsat => {
+                    self.unsat = true;
+                    return Ok(Solution::Unsat);
+                }
+                Status::Interrupted => {
+                    let _ = self.interrupt.take();
+                    return Ok(Solution::Unknown);
+                }
                 Status::Conflict(conflict) => {
+                    self.statistics.conflicts += 1;
                     self.counters.decay_activity();
                     self.num_conflicts += 1;
                     let restart = self.num_conflicts == self.next_restart;
@@ -165,7 +901,15 @@ impl Solver {
                                     } else {
                                         self.backtrack(level);
                                     }
-                                    self.learn_clause(std::iter::once(literal))
+                                    // The unit is already in the proof from
+                                    // when it was first learned
+                                    let id = self.unit_ids[literal.code()].ok_or_else(|| {
+                                        Error::Internal(format!(
+                                            "Unit {} was never learned",
+                                            literal
+                                        ))
+                                    })?;
+                                    self.learn_clause(id, std::iter::once(literal))
                                 },
                             )
                         }
@@ -176,12 +920,20 @@ impl Solver {
                                 } else {
                                     self.backtrack(level);
                                 }
-                                self.learn_clause(learned.into_iter())
+                                let id = self.fresh_id();
+                                let hints = self.learned_hints(clause);
+                                self.prove(id, learned.iter().copied(), &hints)?;
+                                self.record(id, Origin::Derived(hints));
+                                self.learn_clause(id, learned.into_iter())
                             })
                         }
                     };
                     match status {
-                        None => return Ok(Solution::Unsat),
+                        None => {
+                            self.refute(Refutation::Conflict(conflict));
+                            self.prove_unsat()?;
+                            return Ok(Solution::Unsat);
+                        }
                         Some(status) => assert!(matches!(status?, Status::Ok)),
                     }
                 }
@@ -189,6 +941,193 @@ impl Solver {
         }
     }
 
+    /// Adds a clause to the innermost group that is falsified by the current
+    /// assignment, backtracking only as far as needed for it to be satisfied.
+    /// Returns false if the clause can't be satisfied within the group.
+    pub(crate) fn block(&mut self, mut literals: Vec<Literal>) -> Result<bool, Error> {
+        let id = self.fresh_id();
+        if let Some(group) = self.groups.last() {
+            let deactivated = Literal::new(*group, Sign::Negative);
+            self.prove_activated(id, deactivated, &literals)?;
+            literals.push(deactivated);
+        }
+
+        let level = literals
+            .iter()
+            .filter_map(|literal| self.assignments.get(literal.var()))
+            .map(Assignment::decision_level)
+            .max()
+            .unwrap_or(0);
+        self.backtrack(level.saturating_sub(1));
+
+        Ok(matches!(
+            self.learn_clause(id, literals.into_iter())?,
+            Status::Ok
+        ))
+    }
+
+    /// The value assigned to a variable, if any
+    pub(crate) fn value(&self, var: Variable) -> Option<Sign> {
+        self.assignments.get(var).map(Assignment::sign)
+    }
+
+    /// A handle that can be used to stop this solver from another thread
+    pub fn interrupt_handle(&self) -> Interrupt {
+        self.interrupt.clone()
+    }
+
+    /// The work done by the solver across every call so far
+    pub fn statistics(&self) -> Statistics {
+        self.statistics
+    }
+
+    /// Opens a new clause group. Clauses added until the matching `pop` can
+    /// be retracted together, along with anything learned from them.
+    ///
+    /// Each group takes up a fresh variable, so variables created afterwards
+    /// should come from `new_variable` or `add_variables`.
+    pub fn push(&mut self) {
+        let group = self.new_variable();
+        self.activation.insert(group);
+        self.groups.push(group);
+    }
+
+    /// Closes the innermost clause group, removing its clauses and every
+    /// learned clause that depended on them
+    pub fn pop(&mut self) -> Result<(), Error> {
+        let group = self
+            .groups
+            .pop()
+            .ok_or_else(|| Error::InvalidInput("No clause group to pop".to_owned()))?;
+        self.backtrack(0);
+
+        // Learned clauses keep the negated activation literal of every group
+        // they were derived from, since it is only ever decided
+        let deactivated = Literal::new(group, Sign::Negative);
+        let removed = self
+            .formula
+            .remove_containing(deactivated, &mut self.watched);
+        self.pending_update
+            .retain(|clause| !removed.iter().any(|(idx, _, _)| idx == clause));
+        if let Some(originals) = &mut self.originals {
+            originals.retain(|clause| !clause.contains(&deactivated));
+        }
+        if let Some(constraints) = &mut self.original_constraints {
+            constraints
+                .retain(|(terms, _)| terms.iter().all(|(_, literal)| *literal != deactivated));
+        }
+        if let Some(proof) = &mut self.proof {
+            for (_, id, clause) in removed {
+                proof.delete(id, clause.literals())?;
+            }
+        }
+
+        // Permanently disable the group so its variable is never branched on
+        let id = self.fresh_id();
+        self.prove(id, std::iter::once(deactivated), &[])?;
+        self.unit_ids[deactivated.code()] = Some(id);
+        if !matches!(self.assign_invariant(deactivated), Status::Ok) {
+            return Err(Error::Internal(
+                "Clause group was already disabled".to_owned(),
+            ));
+        }
+        Ok(())
+    }
+
+    /// The number of clause groups currently open
+    pub fn num_groups(&self) -> usize {
+        self.groups.len()
+    }
+
+    /// The current assignment, leaving out variables the solver created for
+    /// clause groups
+    fn model(&self) -> Vec<(Variable, Sign)> {
+        self.assignments
+            .assignments()
+            .filter(|(var, _)| !self.activation.contains(*var))
+            .collect()
+    }
+
+    /// The assumptions responsible for the last call to
+    /// `solve_with_assumptions` returning `Unsat`. Empty if the formula is
+    /// unsatisfiable regardless of assumptions.
+    pub fn failed_assumptions(&self) -> &[Literal] {
+        &self.failed
+    }
+
+    /// Finds the first assumption that isn't yet satisfied, which is either
+    /// unassigned (`Ok`) or already falsified (`Err`)
+    fn next_assumption(&self) -> Option<Result<Literal, Literal>> {
+        self.assumptions.iter().find_map(|assumption| {
+            match assumption.evaluate(&self.assignments) {
+                Some(true) => None,
+                Some(false) => Some(Err(*assumption)),
+                None => Some(Ok(*assumption)),
+            }
+        })
+    }
+
+    /// Determines which assumptions forced `failed` to be false by walking
+    /// back through the antecedents of everything assigned since level 0,
+    /// returning them along with the antecedents used and what they implied
+    fn analyze_final(&self, failed: Literal) -> (Vec<Literal>, Vec<(ClauseIdx, Literal)>) {
+        let mut core = vec![failed];
+        let mut antecedents = Vec::new();
+        let mut seen = FixedBitSet::with_capacity(self.num_variables);
+        seen.insert(failed.var());
+
+        for literal in self.history.assigned().iter().rev() {
+            if !seen.contains(literal.var()) {
+                continue;
+            }
+            match self
+                .assignments
+                .get(literal.var())
+                .and_then(Assignment::antecedent)
+            {
+                // Only assumptions are decided while assumptions are pending
+                None => core.push(*literal),
+                Some(antecedent) => {
+                    antecedents.push((antecedent, *literal));
+                    for other in self.formula[antecedent].reason(Some(*literal), &self.assignments)
+                    {
+                        if !self.assignments.assigned_at_level(other.var(), 0) {
+                            seen.insert(other.var());
+                        }
+                    }
+                }
+            }
+        }
+
+        (core, antecedents)
+    }
+
+    /// The IDs of the antecedents used to falsify an assumption, along with
+    /// the units for any of their literals (or the assumption itself) that
+    /// were falsified at decision level 0
+    fn dependencies(
+        &mut self,
+        failed: Literal,
+        antecedents: &[(ClauseIdx, Literal)],
+    ) -> Result<Vec<ClauseId>, Error> {
+        let mut falsified = vec![failed];
+        for (antecedent, implied) in antecedents {
+            falsified.extend(self.formula[*antecedent].reason(Some(*implied), &self.assignments));
+        }
+        falsified.retain(|literal| self.assignments.assigned_at_level(literal.var(), 0));
+        falsified.sort();
+        falsified.dedup();
+
+        let mut dependencies = antecedents
+            .iter()
+            .map(|(antecedent, _)| self.formula.id(*antecedent))
+            .collect::<Vec<_>>();
+        for literal in falsified {
+            dependencies.push(self.unit_id(!literal)?);
+        }
+        Ok(dependencies)
+    }
+
     fn new_decision_level(&mut self) {
         self.decision_level += 1;
         self.history.new_decision_level();
@@ -216,7 +1155,33 @@ impl Solver {
     }
 
     fn propogate_all(&mut self) -> Status {
-        while let Some(literal) = self.history.next_to_propogate() {
+        loop {
+            // Long runs of propagation shouldn't hold up an interrupt
+            if self
+                .statistics
+                .propagations
+                .is_multiple_of(INTERRUPT_CHECK_FREQ)
+                && self.interrupt.is_interrupted()
+            {
+                return Status::Interrupted;
+            }
+
+            let literal = match self.history.next_to_propogate() {
+                Some(literal) => literal,
+                // XOR constraints are only looked at once clauses have
+                // nothing left to propagate
+                None => {
+                    match self.propogate_xors() {
+                        Status::Ok => (),
+                        status => return status,
+                    }
+                    match self.history.next_to_propogate() {
+                        Some(literal) => literal,
+                        None => break,
+                    }
+                }
+            };
+            self.statistics.propagations += 1;
             match self.propogate(literal) {
                 Status::Ok => (),
                 status => return status,
@@ -226,6 +1191,8 @@ impl Solver {
     }
 
     fn propogate(&mut self, literal: Literal) -> Status {
+        self.gauss.assigned(literal.var());
+
         // Find clauses in which negated literal (now unsatisfied) is watched
         self.pending_update.extend(self.watched[!literal].iter());
 
@@ -234,7 +1201,7 @@ impl Solver {
                 clause::Status::Ok => (),
                 clause::Status::Conflict => return Status::Conflict(ConflictType::Clause(clause)),
                 clause::Status::Implied(literal) => match self.assign_implied(literal, clause) {
-                    Status::Ok => (),
+                    Status::Ok => self.requeue(clause),
                     status => return status,
                 },
             }
@@ -243,19 +1210,83 @@ impl Solver {
         Status::Ok
     }
 
+    /// Assigns whatever the XOR constraints imply, adding a clause to explain
+    /// each implication or conflict
+    fn propogate_xors(&mut self) -> Status {
+        while let Some(propagation) = self.gauss.next(&self.assignments) {
+            match propagation {
+                Propagation::Unit(literal) => {
+                    self.backtrack(0);
+                    let id = self.fresh_id();
+                    self.unit_ids[literal.code()] = Some(id);
+                    match self.assign_invariant(literal) {
+                        Status::Ok => (),
+                        status => return status,
+                    }
+                }
+                Propagation::Implied(clause) => {
+                    let literal = clause[0];
+                    let reason = self.add_reason(clause);
+                    match self.assign_implied(literal, reason) {
+                        Status::Ok => (),
+                        status => return status,
+                    }
+                }
+                Propagation::Conflict(clause) => {
+                    // Rows only notice a conflict as their columns are
+                    // assigned, so after a backtrack one may have been
+                    // conflicting since a lower decision level
+                    let level = clause
+                        .iter()
+                        .filter_map(|literal| self.assignments.get(literal.var()))
+                        .map(Assignment::decision_level)
+                        .max()
+                        .unwrap_or(0);
+                    if level < self.decision_level {
+                        self.backtrack(level);
+                    }
+                    return Status::Conflict(ConflictType::Clause(self.add_reason(clause)));
+                }
+            }
+        }
+        Status::Ok
+    }
+
+    /// Adds a clause explaining something the XOR constraints found, which is
+    /// removed once the search backtracks below the current decision level
+    fn add_reason(&mut self, clause: Vec<Literal>) -> ClauseIdx {
+        let id = self.fresh_id();
+        let idx = self.formula.add_reason(id, clause);
+        if self.decision_level > 0 {
+            self.reasons.push((self.decision_level, idx));
+        }
+        idx
+    }
+
+    /// A pseudo-Boolean constraint reports one implied literal at a time, so
+    /// it's updated again after each in case it implies more
+    fn requeue(&mut self, clause: ClauseIdx) {
+        if let clause::Clause::Pb { .. } = self.formula[clause] {
+            self.pending_update.push(clause);
+        }
+    }
+
     fn learn_clause(
         &mut self,
+        id: ClauseId,
         mut clause: impl Iterator<Item = Literal> + ExactSizeIterator,
-    ) -> Result<Status, String> {
+    ) -> Result<Status, Error> {
         match clause.len() {
             0 => Ok(Status::Unsat),
             1 => {
                 let unit = clause.next().unwrap();
                 self.counters.bump(unit.var());
+                self.unit_ids[unit.code()] = Some(id);
                 Ok(self.assign_invariant(unit))
             }
             _ => {
                 let (clause, status) = self.formula.add_clause(
+                    id,
                     clause,
                     &mut self.watched,
                     &mut self.counters,
@@ -275,9 +1306,10 @@ impl Solver {
         self.history.num_assigned() == self.num_variables
     }
 
-    fn branch(&mut self) -> Result<(), String> {
+    fn branch(&mut self) -> Result<(), Error> {
+        self.statistics.decisions += 1;
         self.new_decision_level();
-        let err = || "No variable to branch on".to_owned();
+        let err = || Error::Internal("No variable to branch on".to_owned());
 
         // Get an initial choice, possibly randomly
         let mut var = if self.random_branch.sample(&mut self.rng) {
@@ -294,25 +1326,33 @@ impl Solver {
 
         match self.assign_decided(Literal::new(var, self.assignments.last_sign(var))) {
             Status::Ok => Ok(()),
-            _ => Err("Branched on already decided variable".to_owned()),
+            _ => Err(Error::Internal(
+                "Branched on already decided variable".to_owned(),
+            )),
         }
     }
 
     fn analyze_conflict(
         &mut self,
         conflict_clause: ClauseIdx,
-    ) -> Result<Option<(Vec<Literal>, DecisionLevel)>, String> {
+    ) -> Result<Option<(Vec<Literal>, DecisionLevel)>, Error> {
         let (level, assignments) = (self.decision_level, &self.assignments);
         if level == 0 {
             return Ok(None);
         }
 
         let conflict = &mut self.conflict;
+        self.resolved.clear();
         conflict.initialize(
             self.decision_level,
             &self.formula[conflict_clause],
             assignments,
         );
+        // Propagating a learned unit can falsify a clause of invariants alone,
+        // which refutes the formula whatever has been decided since
+        if conflict.assigned_at_level() == 0 {
+            return Ok(None);
+        }
 
         for literal in self.history.most_recently_implied_at_current_level() {
             if conflict.assigned_at_level() <= 1 {
@@ -328,12 +1368,13 @@ impl Solver {
                             level,
                             assignments.get(literal.var())
                         );
-                        format!(
+                        Error::Internal(format!(
                             "Supposedly implied literal {} was unassigned or had no antecedent",
                             literal
-                        )
+                        ))
                     })?;
                 conflict.resolve(!literal, &self.formula[antecedent], assignments)?;
+                self.resolved.push(antecedent);
             }
         }
 
@@ -342,21 +1383,59 @@ impl Solver {
             .map(|level| (conflict.literals().collect(), level)))
     }
 
+    /// The LRAT hints for the clause just learned from `conflict`. Each
+    /// antecedent resolved on becomes unit in turn, in the order it was
+    /// assigned, once the learned clause is falsified, after which the
+    /// conflict clause is falsified too.
+    fn learned_hints(&self, conflict: ClauseIdx) -> Vec<ClauseId> {
+        if !self.needs_hints() {
+            return Vec::new();
+        }
+        self.resolved
+            .iter()
+            .rev()
+            .chain(std::iter::once(&conflict))
+            .map(|idx| self.formula.id(*idx))
+            .collect()
+    }
+
     fn backtrack(&mut self, level: usize) {
         self.decision_level = level;
         self.history
             .revert_to(level, &mut self.assignments, &mut self.counters);
+        self.gauss.backtracked();
+
+        // Reasons for assignments that have been undone aren't needed anymore
+        let kept = self
+            .reasons
+            .partition_point(|(reason_level, _)| *reason_level <= level);
+        for (_, idx) in self.reasons.drain(kept..) {
+            self.formula.remove(idx);
+        }
     }
 }
 
+/// Adds the clauses of a DIMACS file to a solver
+#[cfg(test)]
+fn load_into(solver: &mut Solver, path: &str) -> Result<(), Error> {
+    solver.add_cnf(&dimacs::parse_file(path)?)
+}
+
+#[cfg(test)]
+fn load(path: &str) -> Result<Solver, Error> {
+    let mut solver = Solver::new();
+    load_into(&mut solver, path)?;
+    Ok(solver)
+}
+
 #[test]
-fn all_variables_assigned_after_propogating() -> Result<(), String> {
+fn all_variables_assigned_after_propogating() -> Result<(), Error> {
     let clauses = vec![
         vec![Literal::new(0, true), Literal::new(1, true)],
         vec![Literal::new(0, false), Literal::new(1, true)],
     ];
     match Solver::solve_clauses(clauses, 2)? {
-        Solution::Unsat => Err("Expected Sat, got Unsat".to_string()),
+        Solution::Unsat | Solution::Unknown => panic!("Expected Sat"),
         Solution::Sat(assignment) => {
             let assignments = assignment.into_iter().collect::<Vec<_>>();
             assert!(assignments
@@ -397,6 +1476,92 @@ fn zebra() {
     assert!(matches!(solution, Ok(Solution::Sat(_))));
 }
 
+#[test]
+fn verify() -> Result<(), Error> {
+    let mut solver = Solver::new();
+    solver.set_verify(true);
+    load_into(&mut solver, "inputs/zebra.cnf")?;
+    let model = match solver.solve()? {
+        Solution::Sat(model) => model,
+        Solution::Unsat | Solution::Unknown => panic!("Expected Sat"),
+    };
+
+    // A wrong model is reported as an error rather than returned
+    let (var, sign) = model[0];
+    if let Some(originals) = &mut solver.originals {
+        originals.push(vec![!Literal::new(var, sign)]);
+    }
+    assert!(matches!(solver.verify_model(), Err(Error::Internal(_))));
+
+    // As is one that falls short of a pseudo-Boolean constraint
+    if let Some(originals) = &mut solver.originals {
+        originals.pop();
+    }
+    assert!(solver.verify_model().is_ok());
+    let (other, other_sign) = model[1];
+    if let Some(constraints) = &mut solver.original_constraints {
+        let terms = vec![
+            (1, !Literal::new(var, sign)),
+            (1, !Literal::new(other, other_sign)),
+        ];
+        constraints.push((terms, 1));
+    }
+    assert!(matches!(solver.verify_model(), Err(Error::Internal(_))));
+    Ok(())
+}
+
+#[test]
+fn unsat_core() -> Result<(), Error> {
+    let cnf = dimacs::parse_file("inputs/aim-100.cnf")?;
+    let mut solver = Solver::new();
+    solver.set_track_core(true);
+    solver.add_cnf(&cnf)?;
+    assert!(matches!(solver.solve()?, Solution::Unsat));
+
+    let core = solver.unsat_core().unwrap();
+    assert!(!core.is_empty() && core.len() < cnf.clauses.len());
+    let mut solver = Solver::new();
+    for idx in core {
+        solver.add_clause(&cnf.clauses[idx])?;
+    }
+    assert!(matches!(solver.solve()?, Solution::Unsat));
+    Ok(())
+}
+
+#[test]
+fn unsat_core_with_assumptions() -> Result<(), Error> {
+    let (a, b, c, d, e) = (
+        Literal::new(0, true),
+        Literal::new(1, true),
+        Literal::new(2, true),
+        Literal::new(3, true),
+        Literal::new(4, true),
+    );
+    let mut solver = Solver::new();
+    solver.set_track_core(true);
+    solver.add_clause(&[!a, b])?;
+    solver.add_clause(&[!b, c])?;
+    solver.add_clause(&[d, e])?;
+    solver.add_clause(&[!d])?;
+
+    assert!(matches!(solver.solve()?, Solution::Sat(_)));
+    assert_eq!(solver.unsat_core(), None);
+
+    assert!(matches!(
+        solver.solve_with_assumptions(&[a, !c])?,
+        Solution::Unsat
+    ));
+    assert_eq!(solver.unsat_core(), Some(vec![0, 1]));
+
+    // Clauses that only matter at decision level 0 are included too
+    assert!(matches!(
+        solver.solve_with_assumptions(&[!e])?,
+        Solution::Unsat
+    ));
+    assert_eq!(solver.unsat_core(), Some(vec![2, 3]));
+    Ok(())
+}
+
 #[test]
 fn dubois() {
     let solution = Solver::parse_and_solve_file("inputs/dubois.cnf");
@@ -420,3 +1585,498 @@ fn bf() {
     let solution = Solver::parse_and_solve_file("inputs/bf0432-007.cnf");
     assert!(matches!(solution, Ok(Solution::Unsat)));
 }
+
+#[test]
+fn incremental() -> Result<(), Error> {
+    let (a, b) = (Literal::new(0, true), Literal::new(1, true));
+    let mut solver = Solver::new();
+
+    solver.add_clause(&[a, b])?;
+    assert!(matches!(solver.solve()?, Solution::Sat(_)));
+
+    solver.add_clause(&[!a])?;
+    match solver.solve()? {
+        Solution::Unsat | Solution::Unknown => panic!("Expected Sat"),
+        Solution::Sat(assignments) => {
+            assert!(assignments.contains(&(0, Sign::Negative)));
+            assert!(assignments.contains(&(1, Sign::Positive)));
+        }
+    }
+
+    solver.add_clause(&[!b, !a])?;
+    assert!(matches!(solver.solve()?, Solution::Sat(_)));
+
+    solver.add_clause(&[!b])?;
+    assert!(matches!(solver.solve()?, Solution::Unsat));
+    assert!(matches!(solver.solve()?, Solution::Unsat));
+    Ok(())
+}
+
+#[test]
+fn new_variables() -> Result<(), Error> {
+    let mut solver = Solver::new();
+    assert_eq!(solver.add_variables(3), 0..3);
+    assert_eq!(solver.new_variable(), 3);
+
+    solver.add_clause(&[Literal::new(6, true), Literal::new(6, true)])?;
+    assert_eq!(solver.num_variables(), 7);
+    match solver.solve()? {
+        Solution::Unsat | Solution::Unknown => panic!("Expected Sat"),
+        Solution::Sat(assignments) => {
+            assert_eq!(assignments.len(), 7);
+            assert!(assignments.contains(&(6, Sign::Positive)));
+            Ok(())
+        }
+    }
+}
+
+#[test]
+fn assumptions() -> Result<(), Error> {
+    let (a, b, c, d) = (
+        Literal::new(0, true),
+        Literal::new(1, true),
+        Literal::new(2, true),
+        Literal::new(3, true),
+    );
+    let mut solver = Solver::new();
+    solver.add_clause(&[!a, b])?;
+    solver.add_clause(&[!b, c])?;
+
+    match solver.solve_with_assumptions(&[!c])? {
+        Solution::Unsat | Solution::Unknown => panic!("Expected Sat"),
+        Solution::Sat(assignments) => {
+            assert!(assignments.contains(&(0, Sign::Negative)));
+            assert!(assignments.contains(&(1, Sign::Negative)));
+        }
+    }
+
+    assert!(matches!(
+        solver.solve_with_assumptions(&[d, a, !c])?,
+        Solution::Unsat
+    ));
+    let mut failed = solver.failed_assumptions().to_vec();
+    failed.sort();
+    assert_eq!(failed, vec![a, !c]);
+
+    // Assumptions don't persist
+    assert!(matches!(solver.solve()?, Solution::Sat(_)));
+    assert!(solver.failed_assumptions().is_empty());
+
+    solver.add_clause(&[!d])?;
+    assert!(matches!(
+        solver.solve_with_assumptions(&[a, d])?,
+        Solution::Unsat
+    ));
+    assert_eq!(solver.failed_assumptions(), &[d]);
+
+    // Units learned above the assumptions' levels can leave a clause of
+    // invariants alone falsified
+    let literal = |x: isize| Literal::from(x);
+    let mut solver = Solver::new();
+    for clause in [
+        vec![-6, 5],
+        vec![-6, 3],
+        vec![-6, -3],
+        vec![-7, 4],
+        vec![-7, -4],
+        vec![-7, -3],
+        vec![6, 7, -8],
+        vec![8],
+    ]
+    .iter()
+    {
+        solver.add_clause(&clause.iter().map(|x| literal(*x)).collect::<Vec<_>>())?;
+    }
+    assert!(matches!(
+        solver.solve_with_assumptions(&[literal(-2), literal(-1)])?,
+        Solution::Unsat
+    ));
+    assert!(solver.failed_assumptions().is_empty());
+    Ok(())
+}
+
+#[test]
+fn assumptions_on_hard_formula() -> Result<(), Error> {
+    let mut solver = load("inputs/aim-50.cnf")?;
+
+    let model = match solver.solve()? {
+        Solution::Unsat | Solution::Unknown => panic!("Expected Sat"),
+        Solution::Sat(assignments) => assignments,
+    };
+
+    // Forbidding the model on a handful of variables at a time should still
+    // leave the formula satisfiable or explain why not
+    for chunk in model.chunks(10) {
+        let assumptions = chunk
+            .iter()
+            .map(|(var, sign)| !Literal::new(*var, *sign))
+            .collect::<Vec<_>>();
+        if let Solution::Unsat = solver.solve_with_assumptions(&assumptions)? {
+            assert!(!solver.failed_assumptions().is_empty());
+            assert!(solver
+                .failed_assumptions()
+                .iter()
+                .all(|literal| assumptions.contains(literal)));
+        }
+    }
+    assert!(matches!(solver.solve()?, Solution::Sat(_)));
+    Ok(())
+}
+
+#[test]
+fn clause_groups() -> Result<(), Error> {
+    let (a, b, c) = (
+        Literal::new(0, true),
+        Literal::new(1, true),
+        Literal::new(2, true),
+    );
+    let mut solver = Solver::new();
+    solver.add_variables(3);
+    solver.add_clause(&[a, b])?;
+
+    solver.push();
+    solver.add_clause(&[!a])?;
+    match solver.solve()? {
+        Solution::Unsat | Solution::Unknown => panic!("Expected Sat"),
+        Solution::Sat(assignments) => {
+            assert_eq!(assignments.len(), 3);
+            assert!(assignments.contains(&(0, Sign::Negative)));
+            assert!(assignments.contains(&(1, Sign::Positive)));
+        }
+    }
+
+    solver.push();
+    solver.add_clause(&[!b, c])?;
+    solver.add_clause(&[!c])?;
+    assert!(matches!(solver.solve()?, Solution::Unsat));
+    assert!(solver.failed_assumptions().is_empty());
+
+    solver.pop()?;
+    assert_eq!(solver.num_groups(), 1);
+    assert!(matches!(solver.solve()?, Solution::Sat(_)));
+
+    solver.add_clause(&[!b])?;
+    assert!(matches!(solver.solve()?, Solution::Unsat));
+    solver.pop()?;
+    match solver.solve()? {
+        Solution::Unsat | Solution::Unknown => panic!("Expected Sat"),
+        Solution::Sat(assignments) => {
+            assert_eq!(assignments.len(), 3);
+            assert!(assignments.contains(&(0, Sign::Positive)));
+        }
+    }
+
+    assert!(matches!(solver.pop(), Err(Error::InvalidInput(_))));
+    Ok(())
+}
+
+#[test]
+fn clause_groups_remove_learned_clauses() -> Result<(), Error> {
+    let mut solver = Solver::new();
+    solver.push();
+    load_into(&mut solver, "inputs/dubois.cnf")?;
+    assert!(matches!(solver.solve()?, Solution::Unsat));
+
+    let group = solver.groups[0];
+    solver.pop()?;
+    let deactivated = Literal::new(group, Sign::Negative);
+    assert!(solver.watched[deactivated].is_empty());
+    assert!(solver.watched[!deactivated].is_empty());
+    assert!(matches!(solver.solve()?, Solution::Sat(_)));
+    Ok(())
+}
+
+#[test]
+fn budget() -> Result<(), Error> {
+    let mut solver = load("inputs/bf0432-007.cnf")?;
+
+    let budget = Budget::unlimited().conflicts(10);
+    assert!(matches!(
+        solver.solve_with_budget(&[], budget)?,
+        Solution::Unknown
+    ));
+    assert_eq!(solver.statistics().conflicts, 10);
+
+    assert!(matches!(
+        solver.solve_with_budget(&[], Budget::unlimited().decisions(0))?,
+        Solution::Unknown
+    ));
+    assert_eq!(solver.statistics().conflicts, 10);
+
+    // Resuming keeps going until the answer is found
+    let mut calls = 0;
+    loop {
+        calls += 1;
+        match solver.solve_with_budget(&[], budget)? {
+            Solution::Unknown => continue,
+            Solution::Unsat => break,
+            Solution::Sat(_) => panic!("Expected Unsat, got Sat"),
+        }
+    }
+    assert!(calls > 1);
+    Ok(())
+}
+
+#[test]
+fn interrupt() -> Result<(), Error> {
+    let mut solver = load("inputs/dubois.cnf")?;
+    let interrupt = solver.interrupt_handle();
+
+    std::thread::spawn(move || interrupt.interrupt())
+        .join()
+        .expect("Interrupting thread panicked");
+    assert!(matches!(solver.solve()?, Solution::Unknown));
+    assert_eq!(solver.statistics(), Statistics::default());
+
+    // The interrupt only stops one call
+    assert!(matches!(solver.solve()?, Solution::Unsat));
+    Ok(())
+}
+
+#[test]
+fn proof() -> Result<(), Error> {
+    use crate::proof::SharedBuffer;
+
+    let buffer = SharedBuffer::default();
+    let mut solver = Solver::new();
+    solver.set_proof(Proof::new(buffer.clone()))?;
+    load_into(&mut solver, "inputs/dubois.cnf")?;
+    assert!(matches!(solver.solve()?, Solution::Unsat));
+
+    let proof = buffer.contents();
+    assert!(proof.ends_with("\n0\n"));
+    assert!(proof
+        .lines()
+        .all(|line| line == "0" || line.ends_with(" 0")));
+    Ok(())
+}
+
+#[test]
+fn lrat_proof() -> Result<(), Error> {
+    use crate::proof::SharedBuffer;
+
+    let buffer = SharedBuffer::default();
+    let mut solver = Solver::new();
+    solver.set_proof(Proof::with_format(buffer.clone(), Format::Lrat))?;
+    let clauses: &[&[isize]] = &[&[1], &[-1, 2], &[-2, 3], &[-3, -1], &[2, 3]];
+    for clause in clauses {
+        let clause = clause.iter().map(|x| Literal::from(*x)).collect::<Vec<_>>();
+        solver.add_clause(&clause)?;
+    }
+    assert!(matches!(solver.solve()?, Solution::Unsat));
+
+    // Units implied at level 0 are derived only once the final conflict
+    // needs them, numbered after every original clause
+    assert_eq!(buffer.contents(), "6 2 0 1 2 0\n7 3 0 6 3 0\n8 0 1 7 4 0\n");
+    Ok(())
+}
+
+#[test]
+fn proof_of_clause_groups() -> Result<(), Error> {
+    use crate::proof::SharedBuffer;
+
+    let (a, b) = (Literal::new(0, true), Literal::new(1, true));
+    let buffer = SharedBuffer::default();
+    let mut solver = Solver::new();
+    solver.set_proof(Proof::new(buffer.clone()))?;
+    solver.add_variables(2);
+    solver.add_clause(&[a])?;
+    solver.push();
+    solver.add_clause(&[!a, b])?;
+    solver.pop()?;
+    solver.add_clause(&[!a])?;
+    assert!(matches!(solver.solve()?, Solution::Unsat));
+
+    // Literals in deleted clauses may have been reordered by watching
+    let proof = buffer.contents();
+    let lines = proof.lines().collect::<Vec<_>>();
+    assert_eq!(lines.len(), 4);
+    assert_eq!(lines[0], "-3 -1 2 0");
+    assert!(lines[1].starts_with("d "));
+    let mut deleted = lines[1].split_whitespace().skip(1).collect::<Vec<_>>();
+    deleted.sort();
+    assert_eq!(deleted, vec!["-1", "-3", "0", "2"]);
+    assert_eq!(&lines[2..], &["-3 0", "0"]);
+    Ok(())
+}
+
+#[test]
+fn pseudo_boolean_constraints() -> Result<(), Error> {
+    let literals = (0..4)
+        .map(|var| Literal::new(var, true))
+        .collect::<Vec<_>>();
+    let (a, b, c, d) = (literals[0], literals[1], literals[2], literals[3]);
+    let mut solver = Solver::new();
+    solver.set_verify(true);
+
+    // 3a + 2b + 2c + d >= 5, so falsifying a forces b, c and d together
+    solver.add_constraint(&[(3, a), (2, b), (2, c), (1, d)], 5)?;
+    match solver.solve_with_assumptions(&[!a])? {
+        Solution::Sat(model) => assert!(model[1..].iter().all(|(_, sign)| *sign == Sign::Positive)),
+        Solution::Unsat | Solution::Unknown => panic!("Expected Sat"),
+    }
+    assert!(matches!(
+        solver.solve_with_assumptions(&[!a, !d])?,
+        Solution::Unsat
+    ));
+    assert!(!solver.failed_assumptions().is_empty());
+
+    // Constraints in a group are retracted with it
+    solver.push();
+    solver.add_constraint(&[(1, !a), (1, !b), (1, !c)], 2)?;
+    solver.add_clause(&[!d])?;
+    assert!(matches!(solver.solve()?, Solution::Unsat));
+    solver.pop()?;
+    assert!(matches!(solver.solve()?, Solution::Sat(_)));
+
+    // Even ones that can never be satisfied
+    solver.push();
+    solver.add_constraint(&[(1, a)], 2)?;
+    assert!(matches!(solver.solve()?, Solution::Unsat));
+    solver.pop()?;
+    assert!(matches!(solver.solve()?, Solution::Sat(_)));
+
+    // Cores would leave them out
+    let mut solver = Solver::new();
+    solver.add_constraint(&[(1, a), (1, b)], 2)?;
+    solver.add_clause(&[!a])?;
+    solver.set_track_core(true);
+    assert!(matches!(solver.solve()?, Solution::Unsat));
+    assert_eq!(solver.unsat_core(), None);
+    assert!(matches!(
+        solver.add_constraint(&[(1, a), (1, b)], 2),
+        Err(Error::InvalidInput(_))
+    ));
+
+    // Constraints can't appear in proofs, whichever comes first
+    solver.set_track_core(false);
+    assert!(matches!(
+        solver.set_proof(Proof::new(std::io::sink())),
+        Err(Error::InvalidInput(_))
+    ));
+    let mut solver = Solver::new();
+    solver.set_proof(Proof::new(std::io::sink()))?;
+    assert!(matches!(
+        solver.add_constraint(&[(1, a), (1, b)], 2),
+        Err(Error::InvalidInput(_))
+    ));
+    Ok(())
+}
+
+#[test]
+fn xor_constraints() -> Result<(), Error> {
+    // a ⊕ b, b ⊕ ¬c and a ⊕ ¬c can't all hold, and a variable given twice
+    // cancels out
+    let (a, b, c) = (
+        Literal::new(0, true),
+        Literal::new(1, true),
+        Literal::new(2, true),
+    );
+    let mut solver = Solver::new();
+    solver.add_xor(&[a, b])?;
+    solver.add_xor(&[b, !c, a, a])?;
+    assert!(matches!(solver.solve()?, Solution::Sat(_)));
+    solver.add_xor(&[a, !c])?;
+    assert!(matches!(solver.solve()?, Solution::Unsat));
+
+    // Cores would leave them out
+    let mut solver = Solver::new();
+    solver.add_xor(&[a, b])?;
+    solver.add_clause(&[a])?;
+    solver.add_clause(&[b])?;
+    solver.set_track_core(true);
+    assert!(matches!(solver.solve()?, Solution::Unsat));
+    assert_eq!(solver.unsat_core(), None);
+    assert!(matches!(
+        solver.add_xor(&[a, b]),
+        Err(Error::InvalidInput(_))
+    ));
+
+    // Nor would proofs, whichever comes first
+    solver.set_track_core(false);
+    assert!(matches!(
+        solver.set_proof(Proof::new(std::io::sink())),
+        Err(Error::InvalidInput(_))
+    ));
+    let mut solver = Solver::new();
+    solver.set_proof(Proof::new(std::io::sink()))?;
+    assert!(matches!(
+        solver.add_xor(&[a, b]),
+        Err(Error::InvalidInput(_))
+    ));
+    Ok(())
+}
+
+#[cfg(test)]
+impl quickcheck::Arbitrary for Cnf {
+    /// A problem small enough to solve by brute force, with about as many XOR
+    /// constraints as variables
+    fn arbitrary<G: quickcheck::Gen>(g: &mut G) -> Self {
+        use rand::Rng;
+
+        let num_variables = g.gen_range(1, 11);
+        let mut random = |len: usize| {
+            (0..len)
+                .map(|_| Literal::new(g.gen_range(0, num_variables), g.gen::<bool>()))
+                .collect::<Vec<_>>()
+        };
+        let xors = (0..num_variables)
+            .map(|_| random(1 + num_variables / 2))
+            .collect();
+        let clauses = (0..num_variables / 2).map(|_| random(3)).collect();
+        Cnf {
+            num_variables,
+            clauses,
+            xors,
+        }
+    }
+}
+
+#[cfg(test)]
+#[quickcheck_macros::quickcheck]
+fn xors_match_brute_force(cnf: Cnf, assumptions: Vec<(Variable, bool)>) -> Result<(), Error> {
+    let Cnf {
+        num_variables,
+        clauses,
+        xors,
+    } = &cnf;
+    let assumptions = assumptions
+        .iter()
+        .take(2)
+        .map(|(var, sign)| Literal::new(var % num_variables, *sign))
+        .collect::<Vec<_>>();
+
+    let satisfied = |model: &[(Variable, Sign)], assumptions: &[Literal]| {
+        let value = |literal: &Literal| model[literal.var()].1 == literal.sign();
+        xors.iter()
+            .all(|xor| xor.iter().filter(|literal| value(literal)).count() % 2 == 1)
+            && clauses.iter().all(|clause| clause.iter().any(value))
+            && assumptions.iter().all(value)
+    };
+    let brute_force = |assumptions: &[Literal]| {
+        (0..1u32 << num_variables).any(|bits| {
+            let model = (0..*num_variables)
+                .map(|var| (var, (bits >> var & 1 == 1).into()))
+                .collect::<Vec<_>>();
+            satisfied(&model, assumptions)
+        })
+    };
+
+    let mut solver = Solver::new();
+    solver.set_verify(true);
+    solver.add_variables(*num_variables);
+    for clause in clauses.iter() {
+        solver.add_clause(clause)?;
+    }
+    for xor in xors.iter() {
+        solver.add_xor(xor)?;
+    }
+    for assumptions in [&assumptions[..], &[]].iter() {
+        match solver.solve_with_assumptions(assumptions)? {
+            Solution::Sat(model) => assert!(satisfied(&model, assumptions)),
+            Solution::Unsat => assert!(!brute_force(assumptions)),
+            Solution::Unknown => panic!("Expected an answer"),
+        }
+    }
+    Ok(())
+}
@@ -11,6 +11,10 @@ impl Watched {
             watched: vec![Vec::new(); num_vars * 2],
         }
     }
+
+    pub fn grow(&mut self, num_vars: Variable) {
+        self.watched.resize(num_vars * 2, Vec::new());
+    }
 }
 
 impl Index<Literal> for Watched {
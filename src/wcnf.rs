@@ -0,0 +1,316 @@
+use crate::{
+    dimacs::{open, read_lines, tokens, ParseError, ParseErrorKind, MAX_VARIABLE},
+    Error, Literal,
+};
+use std::{
+    fmt::{self, Formatter},
+    io::Read,
+    path::Path,
+};
+
+/// A weighted partial MaxSAT problem, as read from a WCNF file
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Wcnf {
+    pub num_variables: usize,
+    /// The clauses every solution has to satisfy
+    pub hard: Vec<Vec<Literal>>,
+    /// The remaining clauses, along with the cost of falsifying each of them
+    pub soft: Vec<(u64, Vec<Literal>)>,
+}
+
+/// The ways in which a WCNF file can fail to be valid, beyond those it shares
+/// with DIMACS
+#[derive(Clone, Debug, PartialEq)]
+pub enum ErrorKind {
+    /// The header isn't of the form `p wcnf <variables> <clauses> [<top>]`
+    MalformedHeader,
+    /// A clause doesn't start with a weight or `h`
+    InvalidWeight,
+}
+
+impl ErrorKind {
+    pub(crate) fn describe(&self, f: &mut Formatter, found: &str) -> fmt::Result {
+        match self {
+            ErrorKind::MalformedHeader => write!(
+                f,
+                "expected a header of the form `p wcnf <variables> <clauses> [<top>]`, found {}",
+                found
+            ),
+            ErrorKind::InvalidWeight => write!(
+                f,
+                "expected a weight (a non-negative integer) or `h`, found {}",
+                found
+            ),
+        }
+    }
+}
+
+pub fn parse_file(path: impl AsRef<Path>) -> Result<Wcnf, Error> {
+    read(open(path.as_ref())?)
+}
+
+/// Reads a problem in WCNF format, decompressing it as in `dimacs::read`
+pub fn read(reader: impl Read) -> Result<Wcnf, Error> {
+    let mut parser = Parser::default();
+    read_lines(reader, |line| parser.parse_line(line))?;
+    Ok(parser.finish())
+}
+
+/// Parses a problem in WCNF format. Both the classic format, with a
+/// `p wcnf <variables> <clauses> <top>` header and every clause starting with
+/// its weight (hard clauses having the top weight or more), and the newer
+/// format without a header, in which hard clauses start with `h`, are
+/// accepted. Comments and clauses are laid out as in `dimacs::parse`.
+pub fn parse(lines: impl IntoIterator<Item = impl AsRef<str>>) -> Result<Wcnf, Error> {
+    let mut parser = Parser::default();
+    for line in lines {
+        parser.parse_line(line.as_ref())?;
+    }
+    Ok(parser.finish())
+}
+
+#[derive(Default)]
+struct Parser {
+    wcnf: Wcnf,
+    /// The number of the last line parsed
+    line: usize,
+    /// The number of variables and top weight declared by a classic header
+    header: Option<(usize, Option<u64>)>,
+    /// The weight of the clause being parsed, which is `None` for a hard
+    /// clause, and its literals so far
+    clause: Option<(Option<u64>, Vec<Literal>)>,
+}
+
+impl Parser {
+    fn parse_line(&mut self, line: &str) -> Result<(), Error> {
+        self.line += 1;
+        let tokens = tokens(line);
+        match tokens.first() {
+            None => return Ok(()),
+            Some((_, token)) if token.starts_with('c') => return Ok(()),
+            Some((_, "p")) if self.header.is_none() && self.is_empty() => {
+                self.header = Some(self.parse_header(line)?);
+                return Ok(());
+            }
+            _ => {}
+        }
+
+        let number = self.line;
+        for (column, token) in tokens {
+            let error = |kind| ParseError {
+                kind,
+                line: number,
+                column,
+                token: token.to_owned(),
+            };
+            let (weight, literals) = match &mut self.clause {
+                Some(clause) => clause,
+                None => {
+                    let weight = match (token, self.header) {
+                        ("h", None) => None,
+                        (_, header) => {
+                            let weight = token.parse::<u64>().map_err(|_| {
+                                error(ParseErrorKind::Wcnf(ErrorKind::InvalidWeight))
+                            })?;
+                            match header {
+                                Some((_, Some(top))) if weight >= top => None,
+                                _ => Some(weight),
+                            }
+                        }
+                    };
+                    self.clause = Some((weight, Vec::new()));
+                    continue;
+                }
+            };
+
+            let literal = token
+                .parse::<isize>()
+                .map_err(|_| error(ParseErrorKind::InvalidLiteral))?;
+            let var = literal
+                .checked_abs()
+                .map(|var| var as usize)
+                .filter(|var| *var <= MAX_VARIABLE)
+                .ok_or_else(|| error(ParseErrorKind::InvalidLiteral))?;
+            match self.header {
+                Some((num_variables, _)) if var > num_variables => {
+                    return Err(error(ParseErrorKind::VariableOutOfRange(num_variables)).into())
+                }
+                _ => self.wcnf.num_variables = self.wcnf.num_variables.max(var),
+            }
+            if literal != 0 {
+                literals.push(Literal::from(literal));
+                continue;
+            }
+            match (*weight, std::mem::take(literals)) {
+                (Some(weight), literals) => self.wcnf.soft.push((weight, literals)),
+                (None, literals) => self.wcnf.hard.push(literals),
+            }
+            self.clause = None;
+        }
+        Ok(())
+    }
+
+    fn parse_header(&self, line: &str) -> Result<(usize, Option<u64>), ParseError> {
+        let tokens = tokens(line);
+        let error = |idx: usize| match tokens.get(idx) {
+            Some((column, token)) => ParseError {
+                kind: ParseErrorKind::Wcnf(ErrorKind::MalformedHeader),
+                line: self.line,
+                column: *column,
+                token: (*token).to_owned(),
+            },
+            None => ParseError {
+                column: line.chars().count() + 1,
+                ..ParseError::at_end(ParseErrorKind::Wcnf(ErrorKind::MalformedHeader), self.line)
+            },
+        };
+
+        if tokens.get(1).map(|(_, token)| *token) != Some("wcnf") {
+            return Err(error(1));
+        }
+        let num_variables = tokens
+            .get(2)
+            .and_then(|(_, token)| token.parse().ok())
+            .filter(|num_variables| *num_variables <= MAX_VARIABLE)
+            .ok_or_else(|| error(2))?;
+        tokens
+            .get(3)
+            .and_then(|(_, token)| token.parse::<usize>().ok())
+            .ok_or_else(|| error(3))?;
+        let top = match tokens.get(4) {
+            Some((_, token)) => Some(token.parse().map_err(|_| error(4))?),
+            None => None,
+        };
+        if tokens.len() > 5 {
+            return Err(error(5));
+        }
+        Ok((num_variables, top))
+    }
+
+    fn is_empty(&self) -> bool {
+        self.clause.is_none() && self.wcnf.hard.is_empty() && self.wcnf.soft.is_empty()
+    }
+
+    /// Returns the problem, including a last clause without a terminating 0
+    fn finish(mut self) -> Wcnf {
+        match self.clause.take() {
+            Some((Some(weight), literals)) => self.wcnf.soft.push((weight, literals)),
+            Some((None, literals)) => self.wcnf.hard.push(literals),
+            None => {}
+        }
+        if let Some((num_variables, _)) = self.header {
+            self.wcnf.num_variables = num_variables;
+        }
+        self.wcnf
+    }
+}
+
+#[test]
+fn classic() -> Result<(), Error> {
+    let literal = |x: isize| Literal::from(x);
+    let wcnf = parse([
+        "c classic format",
+        "p wcnf 3 4 10",
+        "10 1 -2 0",
+        "3 2 0 5 -1",
+        "   3 0",
+        "12 -3 0",
+    ])?;
+    assert_eq!(
+        wcnf,
+        Wcnf {
+            num_variables: 3,
+            hard: vec![vec![literal(1), literal(-2)], vec![literal(-3)]],
+            soft: vec![(3, vec![literal(2)]), (5, vec![literal(-1), literal(3)])],
+        }
+    );
+
+    // Without a top weight every clause is soft
+    let wcnf = parse(["p wcnf 2 2", "4 1 2 0", "1 -1 0"])?;
+    assert!(wcnf.hard.is_empty());
+    assert_eq!(wcnf.soft.len(), 2);
+    Ok(())
+}
+
+#[test]
+fn header_less() -> Result<(), Error> {
+    let literal = |x: isize| Literal::from(x);
+    let wcnf = parse(["c new format", "h 1 2 0", "7 -1 0", "h -2 3 0", "1 -3 0"])?;
+    assert_eq!(
+        wcnf,
+        Wcnf {
+            num_variables: 3,
+            hard: vec![vec![literal(1), literal(2)], vec![literal(-2), literal(3)]],
+            soft: vec![(7, vec![literal(-1)]), (1, vec![literal(-3)])],
+        }
+    );
+    Ok(())
+}
+
+#[test]
+fn wcnf_errors() {
+    use crate::dimacs::parse_error;
+    let error = |lines: &[&str]| parse_error(parse(lines));
+    assert_eq!(
+        error(&["p wcnf 2 1 x"]),
+        (
+            ParseErrorKind::Wcnf(ErrorKind::MalformedHeader),
+            1,
+            12,
+            "x".to_owned()
+        )
+    );
+    assert_eq!(
+        error(&["h 1 0", "-4 2 0"]),
+        (
+            ParseErrorKind::Wcnf(ErrorKind::InvalidWeight),
+            2,
+            1,
+            "-4".to_owned()
+        )
+    );
+    assert_eq!(
+        error(&["p wcnf 2 1 5", "h 1 0"]),
+        (
+            ParseErrorKind::Wcnf(ErrorKind::InvalidWeight),
+            2,
+            1,
+            "h".to_owned()
+        )
+    );
+    assert_eq!(
+        error(&["p wcnf 2 1 5", "5 1 3 0"]),
+        (ParseErrorKind::VariableOutOfRange(2), 2, 5, "3".to_owned())
+    );
+    assert_eq!(
+        error(&["1 1 y 0"]),
+        (ParseErrorKind::InvalidLiteral, 1, 5, "y".to_owned())
+    );
+    assert_eq!(
+        error(&["h 1 -9223372036854775808 0"]),
+        (
+            ParseErrorKind::InvalidLiteral,
+            1,
+            5,
+            "-9223372036854775808".to_owned()
+        )
+    );
+    assert_eq!(
+        error(&["1 4000000000000 0"]),
+        (
+            ParseErrorKind::InvalidLiteral,
+            1,
+            3,
+            "4000000000000".to_owned()
+        )
+    );
+    assert_eq!(
+        error(&["p wcnf 4000000000000 1 5"]),
+        (
+            ParseErrorKind::Wcnf(ErrorKind::MalformedHeader),
+            1,
+            8,
+            "4000000000000".to_owned()
+        )
+    );
+}